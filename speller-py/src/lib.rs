@@ -4,18 +4,24 @@ use pyo3::exceptions::PyException;
 use pyo3::prelude::*;
 use std::collections::HashMap;
 
+/// `(word, start, end, suggestions)`, with char offsets.
+type Misspelling = (String, usize, usize, Vec<String>);
+
 #[pyclass]
 struct Speller(speller_rs::Speller);
 
 #[pymethods]
 impl Speller {
     #[new]
-    #[pyo3(text_signature = "(distance, case_sensitive=False, dict_file=None, dict=None)")]
+    #[pyo3(
+        text_signature = "(distance, case_sensitive=False, dict_file=None, dict=None, restore_case=False)"
+    )]
     fn new(
         distance: i32,
         case_sensitive: bool,
         dict_file: Option<Vec<String>>,
         dict: Option<Vec<HashMap<String, i32>>>,
+        restore_case: Option<bool>,
     ) -> PyResult<Self> {
         let mut speller_builder = speller_rs::Speller::builder();
         speller_builder
@@ -27,6 +33,9 @@ impl Speller {
         if let Some(dict) = dict {
             speller_builder.dict_source(dict);
         }
+        if let Some(restore_case) = restore_case {
+            speller_builder.restore_case(restore_case);
+        }
         let speller = speller_builder
             .build()
             .map_err(|e| BuildError::new_err(e.to_string()))?;
@@ -38,6 +47,48 @@ impl Speller {
         Ok(self.0.correction(word))
     }
 
+    #[pyo3(text_signature = "($self, word, max_results)")]
+    fn suggest(&self, word: &str, max_results: usize) -> PyResult<Vec<(String, u8, i32, f64)>> {
+        Ok(self
+            .0
+            .suggest(word, max_results)
+            .into_iter()
+            .map(|s| (s.word, s.distance, s.count, s.score))
+            .collect())
+    }
+
+    #[pyo3(text_signature = "($self, text)")]
+    fn check_text(&self, text: &str) -> PyResult<Vec<Misspelling>> {
+        Ok(self
+            .0
+            .check_text(text)
+            .into_iter()
+            .map(|m| (m.word, m.char_span.start, m.char_span.end, m.suggestions))
+            .collect())
+    }
+
+    #[pyo3(text_signature = "($self, text)")]
+    fn correct_text(&self, text: &str) -> PyResult<String> {
+        Ok(self.0.correct_text(text))
+    }
+
+    #[pyo3(text_signature = "($self, word, count)")]
+    fn add_word(&mut self, word: &str, count: i32) -> PyResult<()> {
+        self.0.add_word(word, count);
+        Ok(())
+    }
+
+    #[pyo3(text_signature = "($self, word)")]
+    fn remove_word(&mut self, word: &str) -> PyResult<bool> {
+        Ok(self.0.remove_word(word))
+    }
+
+    #[pyo3(text_signature = "($self, word, count)")]
+    fn set_count(&mut self, word: &str, count: i32) -> PyResult<()> {
+        self.0.set_count(word, count);
+        Ok(())
+    }
+
     #[pyo3(text_signature = "($self, word, distance)")]
     fn candidates(&self, word: &str, distance: u8) -> PyResult<Option<Vec<Vec<String>>>> {
         Ok(self.0.candidates(word, distance))
@@ -42,7 +42,7 @@ pub fn edit_distance(a: &str, b: &str, limit: usize) -> Option<usize> {
 
     // If either string is empty, the distance is the length of the other.
     // We know that `b` is the shorter string, so we don't need to check `a`.
-    if b.len() == 0 {
+    if b.is_empty() {
         return Some(min_dist);
     }
 
@@ -93,7 +93,7 @@ pub fn edit_distance_automaton(
     word1: &str,
     word2: &str,
 ) -> Option<u8> {
-    let dfa = automaton_builder.build_dfa(&word1);
+    let dfa = automaton_builder.build_dfa(word1);
     match dfa.eval(word2) {
         Distance::Exact(distance) => Some(distance),
         _ => None,
@@ -11,7 +11,20 @@ fn spellcheck() {
 }
 
 fn criterion_benchmark(c: &mut Criterion) {
-    c.bench_function("spellcheck", |b| b.iter(|| spellcheck()));
+    c.bench_function("spellcheck", |b| b.iter(spellcheck));
+
+    let speller = Speller::builder()
+        .dict_file(vec!["../data/en.json".to_string()])
+        .build()
+        .unwrap();
+    let words = ["helo", "wrold", "speling", "zayraizquierdo_"];
+    c.bench_function("correction", |b| {
+        b.iter(|| {
+            for word in words.iter() {
+                let _ = speller.correction(word);
+            }
+        })
+    });
 }
 
 criterion_group!(
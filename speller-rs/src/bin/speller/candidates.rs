@@ -0,0 +1,36 @@
+use speller_rs::{Misspelling, Speller};
+
+/// The numbered candidates offered for `misspelling`, from [`Speller::candidates`] within
+/// `distance`: closest first, then the best scored suggestions first at each distance, in
+/// the case of the misspelled word. `None` if the word has become known since the text was
+/// checked, such as after adding it to a user dictionary.
+pub fn ranked(
+    speller: &Speller,
+    misspelling: &Misspelling,
+    distance: u8,
+    limit: usize,
+) -> Option<Vec<String>> {
+    let mut candidates = match speller.candidates(&misspelling.word, distance) {
+        Some(candidates) if !candidates[0].is_empty() => return None,
+        Some(candidates) => candidates,
+        None => return Some(vec![]),
+    };
+    let rank = |candidate: &String| {
+        misspelling
+            .suggestions
+            .iter()
+            .position(|suggestion| speller.normalize(suggestion) == *candidate)
+            .unwrap_or(usize::MAX)
+    };
+    for words in candidates.iter_mut() {
+        words.sort_by_key(rank);
+    }
+    Some(
+        candidates
+            .into_iter()
+            .flatten()
+            .take(limit)
+            .map(|candidate| speller.restore_case(&misspelling.word, &candidate))
+            .collect(),
+    )
+}
@@ -0,0 +1,249 @@
+use crate::atomic;
+use crate::candidates;
+use crate::position::LineIndex;
+use speller_rs::code::Language;
+use speller_rs::user::UserDictionary;
+use speller_rs::{Misspelling, Speller};
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+/// Candidates are numbered with a single digit.
+const MAX_CANDIDATES: usize = 10;
+
+const HELP: &str = "\
+  0-9    replace with the numbered candidate
+  a      accept the first candidate
+  Enter  ignore this time
+  i      ignore this time
+  I      ignore everywhere for the rest of the session
+  d      add to the personal dictionary
+  r      type a replacement
+  x      save this file and exit
+  q      quit without saving this file";
+
+/// The personal dictionary of a session: the user dictionary layer of the speller that
+/// added words go to, and the file it is saved to.
+pub struct Personal {
+    pub path: PathBuf,
+    pub layer: usize,
+}
+
+impl Personal {
+    /// Load the word list at `path`, or start an empty one if the file doesn't exist yet, and
+    /// push it onto `speller`.
+    pub fn open(
+        speller: &mut Speller,
+        path: PathBuf,
+        case_sensitive: bool,
+    ) -> io::Result<Personal> {
+        let layer = match UserDictionary::from_file(&path, case_sensitive) {
+            Ok(layer) => layer,
+            Err(_) if !path.exists() => UserDictionary::new(case_sensitive),
+            Err(error) => return Err(io::Error::new(io::ErrorKind::InvalidData, error)),
+        };
+        speller.push_layer(layer);
+        Ok(Personal {
+            path,
+            layer: speller.layers().len() - 1,
+        })
+    }
+
+    pub fn add(&self, speller: &mut Speller, word: &str) {
+        if let Some(layer) = speller.layer_mut(self.layer) {
+            layer.add_word(word, 1);
+        }
+    }
+
+    pub fn save(&self, speller: &Speller) -> io::Result<()> {
+        let mut contents = vec![];
+        if let Some(layer) = speller.layers().get(self.layer) {
+            layer.write_to(&mut contents)?;
+        }
+        atomic::write(&self.path, &contents)
+    }
+}
+
+/// How the user left a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Go on with the next file.
+    Next,
+    /// Stop the session.
+    Exit,
+}
+
+/// What the user chose to do with a misspelling.
+enum Answer {
+    Keep,
+    Replace(String),
+    Exit,
+    Quit,
+}
+
+/// An interactive session that walks the misspellings of files and asks what to do with each,
+/// reading answers from `input` and writing prompts to `output`.
+pub struct Session<'a, R, W> {
+    speller: &'a mut Speller,
+    personal: Option<Personal>,
+    distance: u8,
+    input: R,
+    output: W,
+    /// Words ignored for the rest of the session, normalized as by the speller.
+    ignored: HashSet<String>,
+}
+
+impl<'a, R: BufRead, W: Write> Session<'a, R, W> {
+    pub fn new(
+        speller: &'a mut Speller,
+        personal: Option<Personal>,
+        distance: u8,
+        input: R,
+        output: W,
+    ) -> Session<'a, R, W> {
+        Session {
+            speller,
+            personal,
+            distance,
+            input,
+            output,
+            ignored: HashSet::new(),
+        }
+    }
+
+    /// Walk the misspellings of the file at `path`, the source code of `language` if any, and
+    /// write it back with the replacements, unless the user quits.
+    pub fn correct_file(&mut self, path: &Path, language: Option<Language>) -> io::Result<Outcome> {
+        let text = fs::read_to_string(path)?;
+        let lines = LineIndex::new(&text);
+        let mut corrected = String::with_capacity(text.len());
+        let mut copied = 0;
+        let mut outcome = Outcome::Next;
+        for misspelling in crate::check(self.speller, &text, language) {
+            if self
+                .ignored
+                .contains(&self.speller.normalize(&misspelling.word))
+            {
+                continue;
+            }
+            let candidates =
+                match candidates::ranked(self.speller, &misspelling, self.distance, MAX_CANDIDATES)
+                {
+                    Some(candidates) => candidates,
+                    // Added to the personal dictionary since the file was checked.
+                    None => continue,
+                };
+            self.show(path, &lines, &misspelling, &candidates)?;
+            match self.ask(&misspelling.word, &candidates)? {
+                Answer::Keep => {}
+                Answer::Replace(replacement) => {
+                    corrected.push_str(&text[copied..misspelling.byte_span.start]);
+                    corrected.push_str(&replacement);
+                    copied = misspelling.byte_span.end;
+                }
+                Answer::Exit => {
+                    outcome = Outcome::Exit;
+                    break;
+                }
+                Answer::Quit => return Ok(Outcome::Exit),
+            }
+        }
+        if copied > 0 {
+            corrected.push_str(&text[copied..]);
+            atomic::write(path, corrected.as_bytes())?;
+        }
+        Ok(outcome)
+    }
+
+    fn show(
+        &mut self,
+        path: &Path,
+        lines: &LineIndex,
+        misspelling: &Misspelling,
+        candidates: &[String],
+    ) -> io::Result<()> {
+        let start = lines.position(misspelling.byte_span.start);
+        writeln!(
+            self.output,
+            "\n{}:{}:{}: {}",
+            path.display(),
+            start.line,
+            start.column,
+            misspelling.word
+        )?;
+        // Tabs are shown as spaces to keep the marker under the word.
+        writeln!(
+            self.output,
+            "    {}",
+            lines.line(start.line).replace('\t', " ")
+        )?;
+        writeln!(
+            self.output,
+            "    {}{}",
+            " ".repeat(start.column - 1),
+            "^".repeat(misspelling.word.chars().count())
+        )?;
+        for (number, candidate) in candidates.iter().enumerate() {
+            write!(self.output, "  {number}) {candidate}")?;
+        }
+        if !candidates.is_empty() {
+            writeln!(self.output)?;
+        }
+        Ok(())
+    }
+
+    fn ask(&mut self, word: &str, candidates: &[String]) -> io::Result<Answer> {
+        loop {
+            let answer = match self.prompt("? ")? {
+                Some(answer) => answer,
+                // Out of answers: keep what was done so far.
+                None => return Ok(Answer::Exit),
+            };
+            match answer.as_str() {
+                "" | "i" => return Ok(Answer::Keep),
+                "I" => {
+                    self.ignored.insert(self.speller.normalize(word));
+                    return Ok(Answer::Keep);
+                }
+                "a" if !candidates.is_empty() => return Ok(Answer::Replace(candidates[0].clone())),
+                "d" => match &self.personal {
+                    Some(personal) => {
+                        personal.add(self.speller, word);
+                        personal.save(self.speller)?;
+                        return Ok(Answer::Keep);
+                    }
+                    None => writeln!(
+                        self.output,
+                        "No personal dictionary, start the session with --personal FILE"
+                    )?,
+                },
+                "r" => match self.prompt("Replace with: ")? {
+                    Some(replacement) if !replacement.is_empty() => {
+                        return Ok(Answer::Replace(replacement))
+                    }
+                    _ => {}
+                },
+                "x" => return Ok(Answer::Exit),
+                "q" => return Ok(Answer::Quit),
+                answer => match answer.parse::<usize>() {
+                    Ok(number) if number < candidates.len() => {
+                        return Ok(Answer::Replace(candidates[number].clone()))
+                    }
+                    _ => writeln!(self.output, "{HELP}")?,
+                },
+            }
+        }
+    }
+
+    /// Ask for a line of input. `None` at the end of the input.
+    fn prompt(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        write!(self.output, "{prompt}")?;
+        self.output.flush()?;
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line.trim().to_string()))
+    }
+}
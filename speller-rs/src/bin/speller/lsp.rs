@@ -0,0 +1,422 @@
+//! A Language Server Protocol server over standard input and output.
+//!
+//! Open documents are checked as they change, and each misspelling is published as a
+//! diagnostic. Code actions offer the candidates of a misspelled word as quick fixes, and
+//! add the word to the dictionary of its workspace: a user dictionary file at the root of
+//! each workspace folder, `.speller-words` by default. Documents outside the workspace
+//! folders go to the personal dictionary, if there is one.
+
+use crate::candidates;
+use crate::interactive::Personal;
+use crate::position::LineIndex;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidChangeWorkspaceFolders, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as _, PublishDiagnostics,
+};
+use lsp_types::request::{CodeActionRequest, ExecuteCommand, Request as _};
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOptions, CodeActionOrCommand, CodeActionParams,
+    CodeActionProviderCapability, Command, Diagnostic, DiagnosticSeverity,
+    DidChangeTextDocumentParams, DidChangeWorkspaceFoldersParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, ExecuteCommandOptions, ExecuteCommandParams, InitializeParams,
+    InitializeResult, NumberOrString, Position, PublishDiagnosticsParams, Range,
+    ServerCapabilities, ServerInfo, TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit,
+    Url, WorkspaceEdit, WorkspaceFolder, WorkspaceFoldersServerCapabilities,
+    WorkspaceServerCapabilities,
+};
+use serde_json::Value;
+use speller_rs::code::Language;
+use speller_rs::Speller;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::PathBuf;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+const SOURCE: &str = "speller";
+const ADD_TO_DICTIONARY: &str = "speller.addToDictionary";
+
+/// How the server checks documents.
+pub struct Options {
+    pub distance: u8,
+    /// The number of quick fixes offered for a misspelling.
+    pub suggestions: usize,
+    pub case_sensitive: bool,
+    /// The file name of the dictionaries at the root of the workspace folders.
+    pub workspace_dictionary: String,
+}
+
+/// A workspace folder, with a speller that has the dictionary of the workspace on top.
+struct Workspace {
+    root: PathBuf,
+    speller: Speller,
+    dictionary: Personal,
+}
+
+struct Server {
+    /// The speller of the documents outside the workspace folders.
+    speller: Speller,
+    personal: Option<Personal>,
+    workspaces: Vec<Workspace>,
+    documents: HashMap<Url, String>,
+    options: Options,
+}
+
+/// Serve the client on standard input and output until it shuts the server down.
+pub fn serve(speller: Speller, personal: Option<Personal>, options: Options) -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let (id, params) = connection.initialize_start()?;
+    let params: InitializeParams = serde_json::from_value(params)?;
+    let result = InitializeResult {
+        capabilities: capabilities(),
+        server_info: Some(ServerInfo {
+            name: SOURCE.to_string(),
+            version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        }),
+    };
+    connection.initialize_finish(id, serde_json::to_value(result)?)?;
+
+    let mut server = Server {
+        speller,
+        personal,
+        workspaces: vec![],
+        documents: HashMap::new(),
+        options,
+    };
+    #[allow(deprecated)]
+    let folders = match (params.workspace_folders, params.root_uri) {
+        (Some(folders), _) => folders,
+        (None, Some(uri)) => vec![WorkspaceFolder {
+            uri,
+            name: String::new(),
+        }],
+        (None, None) => vec![],
+    };
+    for folder in folders {
+        server.add_workspace(&folder.uri)?;
+    }
+    server.run(&connection)?;
+    // The writer thread stops once the connection is dropped.
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
+            code_action_kinds: Some(vec![CodeActionKind::QUICKFIX]),
+            ..Default::default()
+        })),
+        execute_command_provider: Some(ExecuteCommandOptions {
+            commands: vec![ADD_TO_DICTIONARY.to_string()],
+            ..Default::default()
+        }),
+        workspace: Some(WorkspaceServerCapabilities {
+            workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+                supported: Some(true),
+                change_notifications: Some(lsp_types::OneOf::Left(true)),
+            }),
+            file_operations: None,
+        }),
+        ..Default::default()
+    }
+}
+
+impl Server {
+    fn run(&mut self, connection: &Connection) -> Result<()> {
+        for message in &connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let id = request.id.clone();
+                    let response = self.request(connection, request).unwrap_or_else(|error| {
+                        let code = ErrorCode::InvalidParams as i32;
+                        Response::new_err(id, code, error.to_string())
+                    });
+                    connection.sender.send(Message::Response(response))?;
+                }
+                // Notifications have no response to carry an error.
+                Message::Notification(notification) => {
+                    if let Err(error) = self.notification(connection, notification) {
+                        eprintln!("speller: {error}");
+                    }
+                }
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn request(&mut self, connection: &Connection, request: Request) -> Result<Response> {
+        let result = match request.method.as_str() {
+            CodeActionRequest::METHOD => {
+                let params: CodeActionParams = serde_json::from_value(request.params)?;
+                serde_json::to_value(self.code_actions(params))?
+            }
+            ExecuteCommand::METHOD => {
+                let params: ExecuteCommandParams = serde_json::from_value(request.params)?;
+                self.execute(connection, params)?;
+                Value::Null
+            }
+            method => {
+                return Ok(Response::new_err(
+                    request.id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("unsupported request {method}"),
+                ))
+            }
+        };
+        Ok(Response::new_ok(request.id, result))
+    }
+
+    fn notification(&mut self, connection: &Connection, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents
+                    .insert(uri.clone(), params.text_document.text);
+                self.publish(connection, &uri)?;
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                // Documents are synced in full, so the last change is the whole text.
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    let uri = params.text_document.uri;
+                    self.documents.insert(uri.clone(), change.text);
+                    self.publish(connection, &uri)?;
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                self.publish(connection, &params.text_document.uri)?;
+            }
+            DidChangeWorkspaceFolders::METHOD => {
+                let params: DidChangeWorkspaceFoldersParams =
+                    serde_json::from_value(notification.params)?;
+                for folder in params.event.removed {
+                    if let Ok(root) = folder.uri.to_file_path() {
+                        self.workspaces.retain(|workspace| workspace.root != root);
+                    }
+                }
+                for folder in params.event.added {
+                    self.add_workspace(&folder.uri)?;
+                }
+                self.publish_all(connection)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Load the dictionary of the workspace folder at `uri`, if it is a local folder.
+    fn add_workspace(&mut self, uri: &Url) -> Result<()> {
+        let Ok(root) = uri.to_file_path() else {
+            return Ok(());
+        };
+        let mut speller = self.speller.clone();
+        let dictionary = Personal::open(
+            &mut speller,
+            root.join(&self.options.workspace_dictionary),
+            self.options.case_sensitive,
+        )?;
+        self.workspaces.push(Workspace {
+            root,
+            speller,
+            dictionary,
+        });
+        Ok(())
+    }
+
+    /// The innermost workspace folder of the document at `uri`.
+    fn workspace(&self, uri: &Url) -> Option<usize> {
+        let path = uri.to_file_path().ok()?;
+        self.workspaces
+            .iter()
+            .enumerate()
+            .filter(|(_, workspace)| path.starts_with(&workspace.root))
+            .max_by_key(|(_, workspace)| workspace.root.components().count())
+            .map(|(index, _)| index)
+    }
+
+    fn speller(&self, uri: &Url) -> &Speller {
+        match self.workspace(uri) {
+            Some(index) => &self.workspaces[index].speller,
+            None => &self.speller,
+        }
+    }
+
+    fn diagnostics(&self, uri: &Url, text: &str) -> Vec<Diagnostic> {
+        let lines = LineIndex::new(text);
+        let speller = self.speller(uri);
+        // Diagnostics don't show suggestions: they are looked up for code actions.
+        let misspellings = match Language::from_path(uri.path()) {
+            Some(language) => speller.find_misspellings_in_code(text, language),
+            None => speller.find_misspellings(text),
+        };
+        misspellings
+            .into_iter()
+            .map(|misspelling| Diagnostic {
+                range: range(
+                    &lines,
+                    misspelling.byte_span.start,
+                    misspelling.byte_span.end,
+                ),
+                severity: Some(DiagnosticSeverity::INFORMATION),
+                code: Some(NumberOrString::String("misspelling".to_string())),
+                source: Some(SOURCE.to_string()),
+                message: format!("Unknown word \"{}\"", misspelling.word),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Publish the diagnostics of the document at `uri`, none if it is closed.
+    fn publish(&self, connection: &Connection, uri: &Url) -> Result<()> {
+        let diagnostics = match self.documents.get(uri) {
+            Some(text) => self.diagnostics(uri, text),
+            None => vec![],
+        };
+        let params = PublishDiagnosticsParams {
+            uri: uri.clone(),
+            diagnostics,
+            version: None,
+        };
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        connection
+            .sender
+            .send(Message::Notification(notification))?;
+        Ok(())
+    }
+
+    fn publish_all(&self, connection: &Connection) -> Result<()> {
+        for uri in self.documents.keys() {
+            self.publish(connection, uri)?;
+        }
+        Ok(())
+    }
+
+    /// The name of the dictionary words of the document at `uri` are added to, if any.
+    fn dictionary_name(&self, uri: &Url) -> Option<&'static str> {
+        match (self.workspace(uri), &self.personal) {
+            (Some(_), _) => Some("workspace"),
+            (None, Some(_)) => Some("personal"),
+            (None, None) => None,
+        }
+    }
+
+    fn code_actions(&self, params: CodeActionParams) -> Vec<CodeActionOrCommand> {
+        let uri = params.text_document.uri;
+        let Some(text) = self.documents.get(&uri) else {
+            return vec![];
+        };
+        let lines = LineIndex::new(text);
+        let speller = self.speller(&uri);
+        let mut actions = vec![];
+        for diagnostic in params.context.diagnostics {
+            if diagnostic.source.as_deref() != Some(SOURCE) {
+                continue;
+            }
+            let start = diagnostic.range.start;
+            let end = diagnostic.range.end;
+            let start = lines.utf16_offset(start.line, start.character);
+            let end = lines.utf16_offset(end.line, end.character);
+            let word = &text[start..end.max(start)];
+            // The word may have been added to a dictionary since.
+            let Some(misspelling) = speller.check_text(word).into_iter().next() else {
+                continue;
+            };
+            let candidates = candidates::ranked(
+                speller,
+                &misspelling,
+                self.options.distance,
+                self.options.suggestions,
+            )
+            .unwrap_or_default();
+            for (rank, candidate) in candidates.into_iter().enumerate() {
+                let edit = TextEdit {
+                    range: diagnostic.range,
+                    new_text: candidate.clone(),
+                };
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: format!("Change to \"{candidate}\""),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: Some(vec![diagnostic.clone()]),
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+                        ..Default::default()
+                    }),
+                    is_preferred: Some(rank == 0),
+                    ..Default::default()
+                }));
+            }
+            if let Some(name) = self.dictionary_name(&uri) {
+                let title = format!("Add \"{word}\" to the {name} dictionary");
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: title.clone(),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: Some(vec![diagnostic.clone()]),
+                    command: Some(Command {
+                        title,
+                        command: ADD_TO_DICTIONARY.to_string(),
+                        arguments: Some(vec![uri.to_string().into(), word.into()]),
+                    }),
+                    ..Default::default()
+                }));
+            }
+        }
+        actions
+    }
+
+    /// Run `speller.addToDictionary` with the document URI and the word as arguments, then
+    /// check the open documents again.
+    fn execute(&mut self, connection: &Connection, params: ExecuteCommandParams) -> Result<()> {
+        if params.command != ADD_TO_DICTIONARY {
+            return Err(format!("unknown command {}", params.command).into());
+        }
+        let (uri, word) = match &params.arguments[..] {
+            [Value::String(uri), Value::String(word)] => (Url::parse(uri)?, word),
+            _ => return Err("expected a document URI and a word".into()),
+        };
+        match self.workspace(&uri) {
+            Some(index) => {
+                let workspace = &mut self.workspaces[index];
+                workspace.dictionary.add(&mut workspace.speller, word);
+                workspace.dictionary.save(&workspace.speller)?;
+            }
+            None => {
+                let personal = self
+                    .personal
+                    .as_ref()
+                    .ok_or("no dictionary to add words to")?;
+                personal.add(&mut self.speller, word);
+                personal.save(&self.speller)?;
+                // The workspace spellers were cloned with the personal layer at the same index.
+                for workspace in self.workspaces.iter_mut() {
+                    personal.add(&mut workspace.speller, word);
+                }
+            }
+        }
+        self.publish_all(connection)
+    }
+}
+
+fn range(lines: &LineIndex, start: usize, end: usize) -> Range {
+    let position = |offset| {
+        let (line, character) = lines.utf16_position(offset);
+        Position { line, character }
+    };
+    Range {
+        start: position(start),
+        end: position(end),
+    }
+}
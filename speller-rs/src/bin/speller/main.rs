@@ -0,0 +1,355 @@
+//! Command-line spell checker for files and standard input.
+//!
+//! Exits with 0 when no misspelling is found, 1 when some are and 2 when a dictionary or an
+//! input can't be read, so it can gate documentation and UI strings in CI. With
+//! `--interactive`, it walks the misspellings of each file instead and asks what to do with
+//! them, ispell style, and with `-a` it speaks the `ispell -a` pipe protocol for editors.
+//! With the `lsp` feature, `--lsp` runs a language server instead.
+//!
+//! Source files are recognized by their extension, and only their identifiers, comments
+//! and string literals are checked, with identifiers split into words.
+
+mod atomic;
+mod candidates;
+mod interactive;
+#[cfg(feature = "lsp")]
+mod lsp;
+mod pipe;
+mod position;
+mod report;
+
+use clap::{Parser, ValueEnum};
+use interactive::{Outcome, Personal, Session};
+use pipe::Pipe;
+use report::{OutputFormat, Reporter};
+use speller_rs::code::Language;
+use speller_rs::error::BuildError;
+use speller_rs::{Misspelling, Speller, MAX_SUGGESTIONS};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::{fs, io};
+
+const EXIT_MISSPELLINGS: u8 = 1;
+const EXIT_ERROR: u8 = 2;
+
+#[derive(Parser)]
+#[command(
+    name = "speller",
+    version,
+    about = "Check the spelling of files or standard input"
+)]
+struct Args {
+    /// Files to check. Standard input is read when there are none, or for `-`.
+    files: Vec<PathBuf>,
+
+    /// Dictionary file, such as en.json or lv.dic. May be given several times.
+    #[arg(
+        short,
+        long = "dict",
+        value_name = "FILE",
+        required_unless_present = "image"
+    )]
+    dicts: Vec<String>,
+
+    /// Speller image written by `Speller::save`, used instead of dictionaries.
+    #[arg(long, value_name = "FILE", conflicts_with = "dicts")]
+    image: Option<PathBuf>,
+
+    /// Bigram and trigram counts for context-sensitive suggestions.
+    #[arg(long = "ngrams", value_name = "FILE")]
+    ngrams: Vec<String>,
+
+    /// Maximum edit distance of suggestions.
+    #[arg(long, default_value_t = 2)]
+    distance: i32,
+
+    /// Treat words that differ in case as different words.
+    #[arg(long)]
+    case_sensitive: bool,
+
+    /// Recognize the inflected forms of Hunspell stems without expanding them.
+    #[arg(long)]
+    morphology: bool,
+
+    /// Output format.
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Human)]
+    format: OutputFormat,
+
+    /// Syntax of the inputs. By default, it follows the extension of each file, and inputs
+    /// that aren't source code are checked as text.
+    #[arg(short, long, value_enum, value_name = "SYNTAX")]
+    language: Option<Syntax>,
+
+    /// Maximum number of suggestions per misspelling.
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    suggestions: usize,
+
+    /// Exit with 0 even when misspellings are found.
+    #[arg(long)]
+    no_fail: bool,
+
+    /// Correct the misspellings of each file interactively and write the file back.
+    #[arg(short, long, requires = "files", conflicts_with_all = ["format", "no_fail"])]
+    interactive: bool,
+
+    /// Personal word list, one `word count` entry per line. Its words are accepted, and the
+    /// words added in interactive and pipe mode are saved to it.
+    #[arg(short, long, value_name = "FILE")]
+    personal: Option<PathBuf>,
+
+    /// Speak the `ispell -a` pipe protocol on standard input and output, for editors.
+    #[arg(
+        short = 'a',
+        long = "pipe",
+        conflicts_with_all = ["files", "interactive", "format", "no_fail"]
+    )]
+    pipe: bool,
+
+    /// Run a Language Server Protocol server on standard input and output.
+    #[cfg(feature = "lsp")]
+    #[arg(
+        long,
+        conflicts_with_all = ["files", "interactive", "pipe", "format", "no_fail"]
+    )]
+    lsp: bool,
+
+    /// File name of the dictionary at the root of each workspace folder, for the language
+    /// server. Words added from a workspace are saved to it.
+    #[cfg(feature = "lsp")]
+    #[arg(long, value_name = "NAME", default_value = ".speller-words")]
+    workspace_dictionary: String,
+
+    /// Accepted for language clients that name the transport.
+    #[cfg(feature = "lsp")]
+    #[arg(long, hide = true)]
+    stdio: bool,
+
+    /// Options editors pass to ispell, accepted and ignored.
+    #[arg(short = 'm', hide = true)]
+    _affixes: bool,
+    #[arg(short = 'B', hide = true)]
+    _run_together: bool,
+    #[arg(short = 'C', hide = true)]
+    _compounds: bool,
+}
+
+/// The syntax of an input: plain text, or the source code of a language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Syntax {
+    Text,
+    Rust,
+    Python,
+    #[value(alias = "js")]
+    Javascript,
+    #[value(alias = "ts")]
+    Typescript,
+    Go,
+}
+
+impl Syntax {
+    fn language(self) -> Option<Language> {
+        match self {
+            Syntax::Text => None,
+            Syntax::Rust => Some(Language::Rust),
+            Syntax::Python => Some(Language::Python),
+            Syntax::Javascript => Some(Language::JavaScript),
+            Syntax::Typescript => Some(Language::TypeScript),
+            Syntax::Go => Some(Language::Go),
+        }
+    }
+}
+
+impl Args {
+    fn speller(&self) -> Result<Speller, BuildError> {
+        if let Some(image) = &self.image {
+            return Speller::load(image);
+        }
+        Speller::builder()
+            .dict_file(self.dicts.clone())
+            .ngram_file(self.ngrams.clone())
+            .distance(self.distance)
+            .case_sensitive(self.case_sensitive)
+            .morphology(self.morphology)
+            .build()
+    }
+
+    fn max_distance(&self) -> u8 {
+        self.distance.clamp(0, u8::MAX as i32) as u8
+    }
+
+    /// The language of the input at `path`, `None` for text.
+    fn language(&self, path: &Path) -> Option<Language> {
+        match self.language {
+            Some(syntax) => syntax.language(),
+            None => Language::from_path(path),
+        }
+    }
+
+    /// The inputs to check, `-` standing for standard input.
+    fn inputs(&self) -> Vec<PathBuf> {
+        if self.files.is_empty() {
+            vec![PathBuf::from("-")]
+        } else {
+            self.files.clone()
+        }
+    }
+}
+
+fn read_input(path: &PathBuf) -> io::Result<String> {
+    if path.as_os_str() == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        Ok(text)
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+/// Check `text` as the source code of `language`, or as text.
+fn check(speller: &Speller, text: &str, language: Option<Language>) -> Vec<Misspelling> {
+    match language {
+        Some(language) => speller.check_code(text, language),
+        None => speller.check_text(text),
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let mut speller = match args.speller() {
+        Ok(speller) => speller,
+        Err(error) => {
+            eprintln!("speller: can't build the speller: {error}");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+    let personal = match &args.personal {
+        Some(path) => match Personal::open(&mut speller, path.clone(), args.case_sensitive) {
+            Ok(personal) => Some(personal),
+            Err(error) => {
+                eprintln!("speller: {}: {error}", path.display());
+                return ExitCode::from(EXIT_ERROR);
+            }
+        },
+        None => None,
+    };
+
+    #[cfg(feature = "lsp")]
+    if args.lsp {
+        return lsp(&args, speller, personal);
+    }
+    if args.pipe {
+        pipe(&args, &mut speller, personal)
+    } else if args.interactive {
+        interactive(&args, &mut speller, personal)
+    } else {
+        report(&args, &speller)
+    }
+}
+
+#[cfg(feature = "lsp")]
+fn lsp(args: &Args, speller: Speller, personal: Option<Personal>) -> ExitCode {
+    let options = lsp::Options {
+        distance: args.max_distance(),
+        suggestions: args.suggestions,
+        case_sensitive: args.case_sensitive,
+        workspace_dictionary: args.workspace_dictionary.clone(),
+    };
+    match lsp::serve(speller, personal, options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("speller: {error}");
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+fn pipe(args: &Args, speller: &mut Speller, personal: Option<Personal>) -> ExitCode {
+    let mut pipe = Pipe::new(
+        speller,
+        personal,
+        args.max_distance(),
+        args.suggestions,
+        io::stdout().lock(),
+    );
+    match pipe.run(io::stdin().lock()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("speller: {error}");
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+fn interactive(args: &Args, speller: &mut Speller, personal: Option<Personal>) -> ExitCode {
+    // Answers are read from standard input, so it can't be checked as well.
+    if args.files.iter().any(|path| path.as_os_str() == "-") {
+        eprintln!("speller: standard input can't be corrected interactively");
+        return ExitCode::from(EXIT_ERROR);
+    }
+    let mut session = Session::new(
+        speller,
+        personal,
+        args.max_distance(),
+        io::stdin().lock(),
+        io::stdout().lock(),
+    );
+    for path in args.files.iter() {
+        match session.correct_file(path, args.language(path)) {
+            Ok(Outcome::Next) => {}
+            Ok(Outcome::Exit) => break,
+            Err(error) => {
+                eprintln!("speller: {}: {error}", path.display());
+                return ExitCode::from(EXIT_ERROR);
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn report(args: &Args, speller: &Speller) -> ExitCode {
+    let mut reporter = Reporter::new(args.format, io::stdout().lock());
+    let mut failed = false;
+    for path in args.inputs() {
+        let name = match path.as_os_str() == "-" {
+            true => "<stdin>".to_string(),
+            false => path.display().to_string(),
+        };
+        let text = match read_input(&path) {
+            Ok(text) => text,
+            Err(error) => {
+                eprintln!("speller: {name}: {error}");
+                failed = true;
+                continue;
+            }
+        };
+        let mut misspellings = check(speller, &text, args.language(&path));
+        for misspelling in misspellings.iter_mut() {
+            if args.suggestions > MAX_SUGGESTIONS {
+                misspelling.suggestions = speller
+                    .suggest(&misspelling.word, args.suggestions)
+                    .into_iter()
+                    .map(|suggestion| suggestion.word)
+                    .collect();
+            } else {
+                misspelling.suggestions.truncate(args.suggestions);
+            }
+        }
+        if let Err(error) = reporter.report(&name, &text, &misspellings) {
+            eprintln!("speller: {error}");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    }
+    if let Err(error) = reporter.finish() {
+        eprintln!("speller: {error}");
+        return ExitCode::from(EXIT_ERROR);
+    }
+
+    if failed {
+        ExitCode::from(EXIT_ERROR)
+    } else if reporter.count() > 0 && !args.no_fail {
+        ExitCode::from(EXIT_MISSPELLINGS)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
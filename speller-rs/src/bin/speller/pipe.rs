@@ -0,0 +1,147 @@
+//! The `ispell -a` pipe protocol, spoken by editors such as Emacs to their spell checker.
+//!
+//! After a banner, each input line is either a command or a line of text to check. For each
+//! word of a text line, the answer is `*` for a correct word, `& word count offset: a, b`
+//! with the candidates of a misspelled word, or `# word offset` when there is none, and an
+//! empty line ends the answer. Offsets are counted in characters from the start of the input
+//! line, a leading `^` included, like ispell 3.1.12 and later.
+
+use crate::candidates;
+use crate::interactive::Personal;
+use speller_rs::tokenize::{tokenize, TokenKind};
+use speller_rs::user::UserDictionary;
+use speller_rs::Speller;
+use std::io;
+use std::io::{BufRead, Write};
+
+/// Editors check the version in the banner to know which protocol to expect.
+const BANNER: &str = concat!(
+    "@(#) International Ispell Version 3.1.20 (but really speller-rs ",
+    env!("CARGO_PKG_VERSION"),
+    ")"
+);
+
+pub struct Pipe<'a, W> {
+    speller: &'a mut Speller,
+    personal: Option<Personal>,
+    /// The layer of the words accepted for the session.
+    session: usize,
+    distance: u8,
+    limit: usize,
+    output: W,
+    /// Whether the `*` of correct words is left out.
+    terse: bool,
+}
+
+impl<'a, W: Write> Pipe<'a, W> {
+    /// Answer on `output` for `speller`, pushing a layer on it for the words accepted during
+    /// the session. At most `limit` candidates are given for a misspelled word.
+    pub fn new(
+        speller: &'a mut Speller,
+        personal: Option<Personal>,
+        distance: u8,
+        limit: usize,
+        output: W,
+    ) -> Pipe<'a, W> {
+        speller.push_layer(UserDictionary::new(false));
+        Pipe {
+            session: speller.layers().len() - 1,
+            speller,
+            personal,
+            distance,
+            limit,
+            output,
+            terse: false,
+        }
+    }
+
+    /// Write the banner, then answer each line of `input` until its end.
+    pub fn run<R: BufRead>(&mut self, input: R) -> io::Result<()> {
+        writeln!(self.output, "{BANNER}")?;
+        self.output.flush()?;
+        for line in input.lines() {
+            self.command(&line?)?;
+            self.output.flush()?;
+        }
+        Ok(())
+    }
+
+    fn command(&mut self, line: &str) -> io::Result<()> {
+        let Some(command) = line.chars().next() else {
+            return self.check(line, 0);
+        };
+        let word = line[command.len_utf8()..].trim();
+        match command {
+            // Add to the personal dictionary, as it is or lowercased.
+            '*' => self.add(word.to_string()),
+            '&' => self.add(word.to_lowercase()),
+            // Accept for the rest of the session.
+            '@' => self.accept(word),
+            '#' => {
+                if let Some(personal) = &self.personal {
+                    personal.save(self.speller)?;
+                }
+            }
+            '!' => self.terse = true,
+            '%' => self.terse = false,
+            // TeX and nroff modes, formatter selection and aspell extensions don't apply.
+            '+' | '-' | '~' | '$' => {}
+            // The rest of the line is text, even if it starts with a command character.
+            '^' => self.check(line, 1)?,
+            _ => self.check(line, 0)?,
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, word: String) {
+        match &self.personal {
+            Some(personal) => personal.add(self.speller, &word),
+            None => self.accept(&word),
+        }
+    }
+
+    fn accept(&mut self, word: &str) {
+        if let Some(layer) = self.speller.layer_mut(self.session) {
+            layer.add_word(word, 1);
+        }
+    }
+
+    /// Answer for each word of `line` after its first `skip` bytes.
+    fn check(&mut self, line: &str, skip: usize) -> io::Result<()> {
+        let text = &line[skip..];
+        let offset = line[..skip].chars().count();
+        let misspellings = self.speller.check_text(text);
+        for token in tokenize(text) {
+            if token.kind != TokenKind::Word {
+                continue;
+            }
+            // A hyphenated word may have several misspelled parts.
+            let mut correct = true;
+            for misspelling in misspellings
+                .iter()
+                .filter(|misspelling| token.byte_span.contains(&misspelling.byte_span.start))
+            {
+                correct = false;
+                let start = offset + misspelling.char_span.start;
+                let candidates =
+                    candidates::ranked(self.speller, misspelling, self.distance, self.limit)
+                        .unwrap_or_default();
+                if candidates.is_empty() {
+                    writeln!(self.output, "# {} {start}", misspelling.word)?;
+                } else {
+                    writeln!(
+                        self.output,
+                        "& {} {} {start}: {}",
+                        misspelling.word,
+                        candidates.len(),
+                        candidates.join(", ")
+                    )?;
+                }
+            }
+            if correct && !self.terse {
+                writeln!(self.output, "*")?;
+            }
+        }
+        writeln!(self.output)
+    }
+}
@@ -0,0 +1,71 @@
+/// Line and column positions of the byte offsets of a text.
+pub struct LineIndex<'a> {
+    text: &'a str,
+    /// Byte offset of the start of each line.
+    starts: Vec<usize>,
+}
+
+/// A 1-based line and column, the column counted in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(text: &'a str) -> LineIndex<'a> {
+        let starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(offset, _)| offset + 1))
+            .collect();
+        LineIndex { text, starts }
+    }
+
+    /// The text of the 1-based `line`, without its line break.
+    pub fn line(&self, line: usize) -> &'a str {
+        let start = self.starts[line - 1];
+        let end = self
+            .starts
+            .get(line)
+            .map_or(self.text.len(), |&next| next - 1);
+        self.text[start..end].trim_end_matches('\r')
+    }
+
+    /// The line and column of the byte `offset`, which must be on a character boundary.
+    pub fn position(&self, offset: usize) -> Position {
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.starts[line];
+        Position {
+            line: line + 1,
+            column: self.text[start..offset].chars().count() + 1,
+        }
+    }
+}
+
+#[cfg(feature = "lsp")]
+impl LineIndex<'_> {
+    /// The 0-based line and column of the byte `offset`, the column counted in UTF-16 code
+    /// units as in the Language Server Protocol.
+    pub fn utf16_position(&self, offset: usize) -> (u32, u32) {
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.starts[line];
+        let column = self.text[start..offset].encode_utf16().count();
+        (line as u32, column as u32)
+    }
+
+    /// The byte offset of a 0-based line and UTF-16 column. Positions past the end of a line
+    /// or of the text are moved back to it.
+    pub fn utf16_offset(&self, line: u32, column: u32) -> usize {
+        let Some(&start) = self.starts.get(line as usize) else {
+            return self.text.len();
+        };
+        let line = self.line(line as usize + 1);
+        let mut units = 0;
+        for (offset, char) in line.char_indices() {
+            if units >= column as usize {
+                return start + offset;
+            }
+            units += char.len_utf16();
+        }
+        start + line.len()
+    }
+}
@@ -0,0 +1,185 @@
+use crate::position::{LineIndex, Position};
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use speller_rs::Misspelling;
+use std::io;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// `path:line:column: word -> suggestions`, one misspelling per line.
+    Human,
+    /// One JSON object per misspelling.
+    Json,
+    /// A single SARIF 2.1.0 log, for code scanning tools.
+    Sarif,
+}
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const RULE_ID: &str = "misspelling";
+
+/// Writes the misspellings of each input in an output format. SARIF results are collected
+/// and written by [`Reporter::finish`], the other formats are written as they come.
+pub struct Reporter<W: Write> {
+    format: OutputFormat,
+    out: W,
+    results: Vec<Value>,
+    count: usize,
+}
+
+impl<W: Write> Reporter<W> {
+    pub fn new(format: OutputFormat, out: W) -> Reporter<W> {
+        Reporter {
+            format,
+            out,
+            results: vec![],
+            count: 0,
+        }
+    }
+
+    /// The number of misspellings reported so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn report(
+        &mut self,
+        path: &str,
+        text: &str,
+        misspellings: &[Misspelling],
+    ) -> io::Result<()> {
+        let lines = LineIndex::new(text);
+        for misspelling in misspellings {
+            let start = lines.position(misspelling.byte_span.start);
+            let end = lines.position(misspelling.byte_span.end);
+            match self.format {
+                OutputFormat::Human => self.human(path, start, misspelling)?,
+                OutputFormat::Json => {
+                    let line = json!({
+                        "path": path,
+                        "line": start.line,
+                        "column": start.column,
+                        "end_column": end.column,
+                        "word": misspelling.word,
+                        "suggestions": misspelling.suggestions,
+                    });
+                    writeln!(self.out, "{line}")?;
+                }
+                OutputFormat::Sarif => {
+                    self.results
+                        .push(sarif_result(path, start, end, misspelling))
+                }
+            }
+        }
+        self.count += misspellings.len();
+        Ok(())
+    }
+
+    fn human(&mut self, path: &str, start: Position, misspelling: &Misspelling) -> io::Result<()> {
+        write!(
+            self.out,
+            "{path}:{}:{}: {}",
+            start.line, start.column, misspelling.word
+        )?;
+        if misspelling.suggestions.is_empty() {
+            writeln!(self.out)
+        } else {
+            writeln!(self.out, " -> {}", misspelling.suggestions.join(", "))
+        }
+    }
+
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.format == OutputFormat::Sarif {
+            let log = json!({
+                "$schema": SARIF_SCHEMA,
+                "version": "2.1.0",
+                "runs": [{
+                    "tool": {
+                        "driver": {
+                            "name": "speller",
+                            "version": env!("CARGO_PKG_VERSION"),
+                            "rules": [{
+                                "id": RULE_ID,
+                                "shortDescription": { "text": "Misspelled word" },
+                            }],
+                        },
+                    },
+                    "columnKind": "unicodeCodePoints",
+                    "results": std::mem::take(&mut self.results),
+                }],
+            });
+            serde_json::to_writer_pretty(&mut self.out, &log)?;
+            writeln!(self.out)?;
+        }
+        self.out.flush()
+    }
+}
+
+fn sarif_result(path: &str, start: Position, end: Position, misspelling: &Misspelling) -> Value {
+    let uri = artifact_uri(path);
+    let region = json!({
+        "startLine": start.line,
+        "startColumn": start.column,
+        "endColumn": end.column,
+    });
+    let message = match misspelling.suggestions.first() {
+        Some(suggestion) => format!(
+            "Unknown word \"{}\". Did you mean \"{suggestion}\"?",
+            misspelling.word
+        ),
+        None => format!("Unknown word \"{}\".", misspelling.word),
+    };
+    let fixes: Vec<Value> = misspelling
+        .suggestions
+        .iter()
+        .map(|suggestion| {
+            json!({
+                "description": { "text": format!("Replace with \"{suggestion}\"") },
+                "artifactChanges": [{
+                    "artifactLocation": { "uri": uri },
+                    "replacements": [{
+                        "deletedRegion": region,
+                        "insertedContent": { "text": suggestion },
+                    }],
+                }],
+            })
+        })
+        .collect();
+    json!({
+        "ruleId": RULE_ID,
+        "level": "warning",
+        "message": { "text": message },
+        "locations": [{
+            "physicalLocation": {
+                "artifactLocation": { "uri": uri },
+                "region": region,
+            },
+        }],
+        "fixes": fixes,
+    })
+}
+
+/// `path` as a URI reference for SARIF: relative paths stay relative, absolute ones become
+/// `file` URIs, and bytes other than unreserved characters and `/` are percent-encoded.
+fn artifact_uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let bytes = path.as_bytes();
+    let mut uri = String::with_capacity(path.len());
+    let mut rest = bytes;
+    if bytes.starts_with(b"/") {
+        uri.push_str("file://");
+    } else if bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && &bytes[1..3] == b":/" {
+        // A Windows drive, such as `C:/`.
+        uri.push_str("file:///");
+        uri.push_str(&path[..2]);
+        rest = &bytes[2..];
+    }
+    for &byte in rest {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    uri
+}
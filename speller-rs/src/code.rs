@@ -0,0 +1,491 @@
+//! Spell checking of source code.
+//!
+//! The comments and string literals of a source file are prose, and its identifiers are
+//! made of words, so [`regions`] finds them with a small lexer for each supported language,
+//! leaving out the keywords of the language. Identifiers such as `parseHttpRequest` or
+//! `MAX_RETRY_COUNT`, in the code or mentioned in prose, are broken into the words they are
+//! made of by [`split_identifier`].
+
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::ops::Range;
+use std::path::Path;
+
+/// A programming language whose identifiers, comments and string literals can be found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Go,
+}
+
+impl Language {
+    /// The language of source files with `extension`, such as `rs` or `tsx`.
+    pub fn from_extension(extension: &str) -> Option<Language> {
+        match extension {
+            "rs" => Some(Language::Rust),
+            "py" | "pyi" => Some(Language::Python),
+            "js" | "jsx" | "mjs" | "cjs" => Some(Language::JavaScript),
+            "ts" | "tsx" | "mts" | "cts" => Some(Language::TypeScript),
+            "go" => Some(Language::Go),
+            _ => None,
+        }
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Language> {
+        let extension = path.as_ref().extension().and_then(OsStr::to_str)?;
+        Language::from_extension(extension)
+    }
+
+    fn line_comment(self) -> &'static str {
+        match self {
+            Language::Python => "#",
+            _ => "//",
+        }
+    }
+
+    fn has_block_comments(self) -> bool {
+        self != Language::Python
+    }
+
+    /// Whether `word` is a keyword of the language, a primitive type or a built-in name,
+    /// which is not checked as an identifier.
+    fn is_keyword(self, word: &str) -> bool {
+        let keywords = match self {
+            Language::Rust => RUST_KEYWORDS,
+            Language::Python => PYTHON_KEYWORDS,
+            Language::JavaScript => JAVASCRIPT_KEYWORDS,
+            Language::TypeScript if is_listed(TYPESCRIPT_KEYWORDS, word) => return true,
+            Language::TypeScript => JAVASCRIPT_KEYWORDS,
+            Language::Go => GO_KEYWORDS,
+        };
+        is_listed(keywords, word)
+    }
+}
+
+const RUST_KEYWORDS: &str =
+    "as async await break const continue crate dyn else enum extern false fn for if impl in \
+    let loop match mod move mut pub ref return self Self static struct super trait true \
+    type union unsafe use where while bool char str i8 i16 i32 i64 i128 isize u8 u16 u32 \
+    u64 u128 usize f32 f64 Vec Ok Err Some None println eprintln vec";
+
+const PYTHON_KEYWORDS: &str =
+    "and as assert async await break class continue def del elif else except False finally \
+    for from global if import in is lambda None nonlocal not or pass raise return True try \
+    while with yield self cls bool bytes dict float int len str tuple isinstance kwargs";
+
+const JAVASCRIPT_KEYWORDS: &str =
+    "async await break case catch class const continue debugger default delete do else \
+    export extends false finally for function if import in instanceof let new null of \
+    return static super switch this throw true try typeof undefined var void while with \
+    yield NaN console JSON";
+
+/// The keywords TypeScript adds to those of JavaScript.
+const TYPESCRIPT_KEYWORDS: &str =
+    "abstract any bigint boolean declare enum implements infer interface keyof namespace \
+    never number private protected public readonly string symbol type unknown";
+
+const GO_KEYWORDS: &str =
+    "break case chan const continue default defer else fallthrough for func go goto if \
+    import interface map package range return select struct switch type var true false \
+    iota nil bool byte rune string error int int8 int16 int32 int64 uint uint8 uint16 \
+    uint32 uint64 uintptr float32 float64 complex64 complex128 append cap copy len make \
+    panic recover println";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    /// A name in the code, such as a function or a variable, that is not a keyword.
+    Identifier,
+    Comment,
+    String,
+    /// A string literal without escape sequences, such as `r"..."` in Rust or a backquoted
+    /// string in Go.
+    RawString,
+}
+
+/// An identifier, or the text of a comment or a string literal without its delimiters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub kind: RegionKind,
+    pub byte_span: Range<usize>,
+}
+
+/// Find the identifiers, comments and string literals of `text`, in order. Keywords and
+/// number literals are left out. An unterminated comment or string runs to the end of the
+/// text, or of the line for single-line strings.
+pub fn regions(text: &str, language: Language) -> Vec<Region> {
+    let mut lexer = Lexer {
+        text,
+        bytes: text.as_bytes(),
+        language,
+        regions: vec![],
+    };
+    let mut i = 0;
+    while i < lexer.bytes.len() {
+        i = lexer.next(i);
+    }
+    lexer.regions
+}
+
+struct Lexer<'a> {
+    text: &'a str,
+    bytes: &'a [u8],
+    language: Language,
+    regions: Vec<Region>,
+}
+
+impl Lexer<'_> {
+    /// Lex the token at byte `i` and return the byte after it.
+    fn next(&mut self, i: usize) -> usize {
+        let rest = &self.bytes[i..];
+        let line_comment = self.language.line_comment().as_bytes();
+        if rest.starts_with(line_comment) {
+            let start = i + line_comment.len();
+            let end = self.find(start, b"\n").unwrap_or(self.bytes.len());
+            return self.push(RegionKind::Comment, start, end, end);
+        }
+        if self.language.has_block_comments() && rest.starts_with(b"/*") {
+            return self.block_comment(i + 2);
+        }
+        if let Some(next) = self.string(i) {
+            return next;
+        }
+        let byte = self.bytes[i];
+        if is_identifier_byte(byte) {
+            // Take the whole identifier, so that a quote after it doesn't start a prefixed
+            // string and a `'` in Rust is only taken for a char literal at a token start.
+            let end = self.identifier_end(i);
+            let identifier = &self.text[i..end];
+            if byte.is_ascii_digit() || self.language.is_keyword(identifier) {
+                return end;
+            }
+            return self.push(RegionKind::Identifier, i, end, end);
+        }
+        if byte == b'\'' && self.language == Language::Rust {
+            return self.rust_char(i);
+        }
+        if byte == b'/' && matches!(self.language, Language::JavaScript | Language::TypeScript) {
+            return self.regex(i);
+        }
+        i + 1
+    }
+
+    fn push(&mut self, kind: RegionKind, start: usize, end: usize, next: usize) -> usize {
+        if start < end {
+            self.regions.push(Region {
+                kind,
+                byte_span: start..end,
+            });
+        }
+        next
+    }
+
+    fn identifier_end(&self, start: usize) -> usize {
+        let length = self.bytes[start..]
+            .iter()
+            .take_while(|&&b| is_identifier_byte(b))
+            .count();
+        start + length
+    }
+
+    fn find(&self, from: usize, needle: &[u8]) -> Option<usize> {
+        self.bytes[from..]
+            .windows(needle.len())
+            .position(|window| window == needle)
+            .map(|position| from + position)
+    }
+
+    /// A block comment whose content starts at `start`. Rust block comments nest.
+    fn block_comment(&mut self, start: usize) -> usize {
+        let mut depth = 1;
+        let mut i = start;
+        while i < self.bytes.len() {
+            let rest = &self.bytes[i..];
+            if rest.starts_with(b"*/") {
+                depth -= 1;
+                if depth == 0 {
+                    return self.push(RegionKind::Comment, start, i, i + 2);
+                }
+                i += 2;
+            } else if rest.starts_with(b"/*") && self.language == Language::Rust {
+                depth += 1;
+                i += 2;
+            } else {
+                i += 1;
+            }
+        }
+        let end = self.bytes.len();
+        self.push(RegionKind::Comment, start, end, end)
+    }
+
+    /// The string literal starting at `i`, if any, with its prefix.
+    fn string(&mut self, i: usize) -> Option<usize> {
+        let prefix = self.bytes[i..]
+            .iter()
+            .take_while(|b| b.is_ascii_alphabetic())
+            .count();
+        let prefix = std::str::from_utf8(&self.bytes[i..i + prefix]).unwrap_or_default();
+        let at = i + prefix.len();
+        let quote = *self.bytes.get(at)?;
+        match self.language {
+            Language::Rust => {
+                let raw = matches!(prefix, "r" | "br" | "cr");
+                if raw && matches!(quote, b'"' | b'#') {
+                    let hashes = self.bytes[at..].iter().take_while(|&&b| b == b'#').count();
+                    if self.bytes.get(at + hashes) != Some(&b'"') {
+                        return None;
+                    }
+                    let mut closing = vec![b'"'];
+                    closing.resize(hashes + 1, b'#');
+                    let start = at + hashes + 1;
+                    return Some(self.delimited(RegionKind::RawString, start, &closing, true));
+                }
+                if quote == b'"' && matches!(prefix, "" | "b" | "c") {
+                    return Some(self.delimited(RegionKind::String, at + 1, b"\"", true));
+                }
+                None
+            }
+            Language::Python => {
+                let prefix = prefix.to_ascii_lowercase();
+                let valid = prefix.len() <= 2 && prefix.chars().all(|c| "rbfu".contains(c));
+                if !valid || !matches!(quote, b'"' | b'\'') {
+                    return None;
+                }
+                let kind = match prefix.contains('r') {
+                    true => RegionKind::RawString,
+                    false => RegionKind::String,
+                };
+                let triple = [quote; 3];
+                if self.bytes[at..].starts_with(&triple) {
+                    return Some(self.delimited(kind, at + 3, &triple, true));
+                }
+                Some(self.delimited(kind, at + 1, &[quote], false))
+            }
+            Language::JavaScript | Language::TypeScript if prefix.is_empty() => match quote {
+                b'"' | b'\'' => Some(self.delimited(RegionKind::String, at + 1, &[quote], false)),
+                b'`' => Some(self.delimited(RegionKind::String, at + 1, b"`", true)),
+                _ => None,
+            },
+            Language::Go if prefix.is_empty() => match quote {
+                b'"' => Some(self.delimited(RegionKind::String, at + 1, b"\"", false)),
+                b'`' => Some(self.delimited(RegionKind::RawString, at + 1, b"`", true)),
+                // A rune holds no words.
+                b'\'' => Some(self.skip_quoted(at + 1, b'\'')),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// A string whose content starts at `start` and ends before `closing`.
+    fn delimited(
+        &mut self,
+        kind: RegionKind,
+        start: usize,
+        closing: &[u8],
+        multiline: bool,
+    ) -> usize {
+        // A backslash still escapes the quote in a raw string of Python.
+        let escapes = kind == RegionKind::String || self.language == Language::Python;
+        let mut i = start;
+        while i < self.bytes.len() {
+            let rest = &self.bytes[i..];
+            if escapes && rest[0] == b'\\' {
+                i += 2;
+            } else if rest.starts_with(closing) {
+                return self.push(kind, start, i, i + closing.len());
+            } else if rest[0] == b'\n' && !multiline {
+                return self.push(kind, start, i, i);
+            } else {
+                i += 1;
+            }
+        }
+        let end = self.bytes.len();
+        self.push(kind, start, end, end)
+    }
+
+    /// Skip past the unescaped `quote` that closes a literal starting at `start`, without
+    /// crossing a line.
+    fn skip_quoted(&self, start: usize, quote: u8) -> usize {
+        let mut i = start;
+        while i < self.bytes.len() && self.bytes[i] != b'\n' {
+            match self.bytes[i] {
+                b'\\' => i += 2,
+                b if b == quote => return i + 1,
+                _ => i += 1,
+            }
+        }
+        i.min(self.bytes.len())
+    }
+
+    /// A char literal such as `'a'` or `'\n'`, or a lifetime such as `'a`, whose name is not
+    /// checked.
+    fn rust_char(&self, i: usize) -> usize {
+        if self.bytes.get(i + 1) == Some(&b'\\') {
+            return self.skip_quoted(i + 1, b'\'');
+        }
+        let length = self.text[i + 1..].chars().next().map_or(1, char::len_utf8);
+        if self.bytes.get(i + 1 + length) == Some(&b'\'') {
+            i + 2 + length
+        } else {
+            self.identifier_end(i + 1)
+        }
+    }
+
+    /// A regular expression literal, whose quotes don't start strings, or a division. A `/`
+    /// starts a regular expression where an operand is expected.
+    fn regex(&self, i: usize) -> usize {
+        let previous = self.bytes[..i]
+            .iter()
+            .rev()
+            .find(|b| !b.is_ascii_whitespace());
+        let operand_expected = match previous {
+            None => true,
+            Some(b) => b"(,=:[!&|?{};+-*%<>~^".contains(b),
+        };
+        if !operand_expected {
+            return i + 1;
+        }
+        let mut in_class = false;
+        let mut j = i + 1;
+        while j < self.bytes.len() && self.bytes[j] != b'\n' {
+            match self.bytes[j] {
+                b'\\' => j += 1,
+                b'[' => in_class = true,
+                b']' => in_class = false,
+                // The flags of the expression are not a name.
+                b'/' if !in_class => return self.identifier_end(j + 1),
+                _ => {}
+            }
+            j += 1;
+        }
+        // Not a regular expression after all.
+        i + 1
+    }
+}
+
+fn is_listed(words: &str, word: &str) -> bool {
+    words.split_ascii_whitespace().any(|listed| listed == word)
+}
+
+fn is_identifier_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || !byte.is_ascii()
+}
+
+/// The text of a region as prose: escape sequences and printf-style verbs, such as `\n` and
+/// `%s`, are blanked out so they don't stick to the words around them. Byte offsets are kept.
+pub fn prose(text: &str, kind: RegionKind) -> Cow<'_, str> {
+    if kind != RegionKind::String || !text.contains(['\\', '%']) {
+        return Cow::Borrowed(text);
+    }
+    let bytes = text.as_bytes();
+    let mut blank = vec![false; bytes.len()];
+    let mut i = 0;
+    while i < bytes.len() {
+        let length = match bytes[i] {
+            b'\\' => escape_length(&bytes[i..]),
+            b'%' => verb_length(&bytes[i..]),
+            _ => 0,
+        };
+        for flag in blank.iter_mut().skip(i).take(length) {
+            *flag = true;
+        }
+        i += length.max(1);
+    }
+    // Only ASCII bytes are blanked, so the text stays valid UTF-8.
+    let prose: Vec<u8> = bytes
+        .iter()
+        .zip(blank)
+        .map(|(&b, blank)| if blank && b.is_ascii() { b' ' } else { b })
+        .collect();
+    Cow::Owned(String::from_utf8(prose).unwrap_or_else(|_| text.to_string()))
+}
+
+/// The length of the escape sequence at the start of `bytes`, such as `\n`, `\x41` or
+/// `\u{1F600}`.
+fn escape_length(bytes: &[u8]) -> usize {
+    let hex = |max: usize| {
+        bytes[2..]
+            .iter()
+            .take(max)
+            .take_while(|b| b.is_ascii_hexdigit())
+            .count()
+    };
+    match bytes.get(1) {
+        None => 1,
+        Some(b'x') => 2 + hex(2),
+        Some(b'u') | Some(b'N') if bytes.get(2) == Some(&b'{') => bytes
+            .iter()
+            .position(|&b| b == b'}')
+            .map_or(bytes.len(), |end| end + 1),
+        Some(b'u') => 2 + hex(4),
+        Some(b'U') => 2 + hex(8),
+        Some(b'0'..=b'7') => {
+            1 + bytes[1..]
+                .iter()
+                .take(3)
+                .take_while(|b| (b'0'..=b'7').contains(b))
+                .count()
+        }
+        Some(_) => 2,
+    }
+}
+
+/// The length of the printf-style verb at the start of `bytes`, such as `%s` or `%-8.2f`,
+/// or 0 if there is none. A verb ends its word, so the `%` of `100% sure` or `50% of` is
+/// left alone.
+fn verb_length(bytes: &[u8]) -> usize {
+    let flags = bytes[1..]
+        .iter()
+        .take_while(|b| b"-+#0123456789.".contains(b))
+        .count();
+    match (bytes.get(1 + flags), bytes.get(2 + flags)) {
+        (Some(b), next)
+            if b"cdefgioqsuvxEGX".contains(b) && !next.is_some_and(u8::is_ascii_alphabetic) =>
+        {
+            2 + flags
+        }
+        _ => 0,
+    }
+}
+
+/// The byte spans of the words of an identifier: `parseHttpRequest` is made of `parse`,
+/// `Http` and `Request`, `HTTPServer` of `HTTP` and `Server`, and `MAX_RETRY_COUNT` of
+/// `MAX`, `RETRY` and `COUNT`. Underscores, hyphens and digits separate words. A plain word
+/// is a single span.
+pub fn split_identifier(identifier: &str) -> Vec<Range<usize>> {
+    let chars: Vec<(usize, char)> = identifier.char_indices().collect();
+    let mut words = vec![];
+    let mut start: Option<usize> = None;
+    for (index, &(offset, c)) in chars.iter().enumerate() {
+        if !c.is_alphabetic() && c != '\'' && c != '’' {
+            if let Some(start) = start.take() {
+                words.push(start..offset);
+            }
+            continue;
+        }
+        if let Some(word_start) = start {
+            let previous = chars[index - 1].1;
+            let next = chars.get(index + 1).map(|&(_, c)| c);
+            let after_next = chars.get(index + 2).map(|&(_, c)| c);
+            // The plural of an acronym, as in "URLs", is a single word.
+            let plural = next == Some('s') && !after_next.is_some_and(char::is_lowercase);
+            // "parseHttp" splits before "H", and "HTTPServer" before "S".
+            let boundary = c.is_uppercase()
+                && (previous.is_lowercase()
+                    || previous.is_uppercase() && next.is_some_and(char::is_lowercase) && !plural);
+            if boundary {
+                words.push(word_start..offset);
+                start = Some(offset);
+            }
+        } else {
+            start = Some(offset);
+        }
+    }
+    if let Some(start) = start {
+        words.push(start..identifier.len());
+    }
+    words
+}
@@ -0,0 +1,134 @@
+//! Transparent decompression of dictionary files.
+//!
+//! Compressed input is recognized by its magic bytes, so a gzip file is decompressed whatever
+//! its name. Each format is behind a cargo feature: `gzip`, `zstd` and `xz`. Without the
+//! feature, a compressed file fails with [`BuildError::CompressionNotEnabled`].
+
+use crate::error::BuildError;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// A compression format, with its magic bytes and file extensions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+impl Compression {
+    const ALL: [Compression; 3] = [Compression::Gzip, Compression::Zstd, Compression::Xz];
+
+    fn magic(self) -> &'static [u8] {
+        match self {
+            Compression::Gzip => &[0x1f, 0x8b],
+            Compression::Zstd => &[0x28, 0xb5, 0x2f, 0xfd],
+            Compression::Xz => &[0xfd, b'7', b'z', b'X', b'Z', 0x00],
+        }
+    }
+
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Compression::Gzip => &["gz", "gzip"],
+            Compression::Zstd => &["zst", "zstd"],
+            Compression::Xz => &["xz"],
+        }
+    }
+
+    /// The cargo feature that enables the format.
+    pub fn feature(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Xz => "xz",
+        }
+    }
+
+    /// The format whose magic bytes start `bytes`.
+    pub fn detect(bytes: &[u8]) -> Option<Compression> {
+        Compression::ALL
+            .into_iter()
+            .find(|compression| bytes.starts_with(compression.magic()))
+    }
+
+    /// The format named by the extension of `path`, such as `gz` in `en.json.gz`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Compression> {
+        let extension = path.as_ref().extension().and_then(OsStr::to_str)?;
+        Compression::ALL
+            .into_iter()
+            .find(|compression| compression.extensions().contains(&extension))
+    }
+}
+
+/// `path` without its compression extension, so `en.json.gz` gives `en.json`. Other paths
+/// are returned as they are.
+pub fn strip_extension<P: AsRef<Path>>(path: P) -> PathBuf {
+    let path = path.as_ref();
+    match Compression::from_path(path) {
+        Some(_) => path.with_extension(""),
+        None => path.to_path_buf(),
+    }
+}
+
+/// The length of the longest magic bytes.
+const MAGIC_LENGTH: usize = 6;
+
+/// Decompress `reader` if it starts with the magic bytes of a compression format, or return
+/// it as it is.
+pub fn decompress<'a, R: BufRead + 'a>(mut reader: R) -> Result<Box<dyn BufRead + 'a>, BuildError> {
+    if reader.fill_buf()?.len() >= MAGIC_LENGTH {
+        let compression = Compression::detect(reader.fill_buf()?);
+        return wrap(reader, compression);
+    }
+    // A pipe or a small reader may hand over fewer bytes at a time: read them one buffer
+    // after the other, then put them back in front of the rest.
+    let mut start = Vec::with_capacity(MAGIC_LENGTH);
+    while start.len() < MAGIC_LENGTH {
+        let buffer = match reader.fill_buf() {
+            Ok(buffer) => buffer,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error.into()),
+        };
+        if buffer.is_empty() {
+            break;
+        }
+        let length = buffer.len().min(MAGIC_LENGTH - start.len());
+        start.extend_from_slice(&buffer[..length]);
+        reader.consume(length);
+    }
+    let compression = Compression::detect(&start);
+    wrap(io::Cursor::new(start).chain(reader), compression)
+}
+
+fn wrap<'a, R: BufRead + 'a>(
+    reader: R,
+    compression: Option<Compression>,
+) -> Result<Box<dyn BufRead + 'a>, BuildError> {
+    let Some(compression) = compression else {
+        return Ok(Box::new(reader));
+    };
+    match compression {
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Ok(Box::new(BufReader::new(
+            flate2::bufread::MultiGzDecoder::new(reader),
+        ))),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Ok(Box::new(BufReader::new(
+            zstd::stream::read::Decoder::with_buffer(reader)?,
+        ))),
+        #[cfg(feature = "xz")]
+        Compression::Xz => Ok(Box::new(BufReader::new(
+            xz2::bufread::XzDecoder::new_multi_decoder(reader),
+        ))),
+        #[allow(unreachable_patterns)]
+        compression => Err(BuildError::CompressionNotEnabled(compression.feature())),
+    }
+}
+
+/// Open the file at `path`, decompressing it if needed.
+pub fn open<P: AsRef<Path>>(path: P) -> Result<Box<dyn BufRead>, BuildError> {
+    decompress(BufReader::new(File::open(path)?))
+}
@@ -13,6 +13,19 @@ pub enum BuildError {
     TXTIndexError,
     ParseCountError,
     DictNotFound,
+    /// The file is not a speller image, or it is truncated.
+    InvalidImage,
+    UnsupportedImageVersion(u32),
+    ChecksumMismatch,
+    /// A malformed line in a Hunspell `.aff` file.
+    InvalidAffixFile,
+    UnsupportedEncoding(String),
+    /// The input is compressed in a format whose cargo feature is not enabled.
+    CompressionNotEnabled(&'static str),
+    /// A dictionary reader was already read by a previous build.
+    ReaderConsumed,
+    /// A line of an error model file without a correction, with its 1-based number.
+    InvalidErrorModel(usize),
 }
 
 impl From<io::Error> for BuildError {
@@ -49,6 +62,24 @@ impl std::fmt::Display for BuildError {
             BuildError::TXTIndexError => write!(f, "TXT index error"),
             BuildError::ParseCountError => write!(f, "Error parsing count"),
             BuildError::DictNotFound => write!(f, "Dictionary not found"),
+            BuildError::InvalidImage => write!(f, "Invalid speller image"),
+            BuildError::UnsupportedImageVersion(version) => {
+                write!(f, "Unsupported speller image version {version}")
+            }
+            BuildError::ChecksumMismatch => write!(f, "Speller image checksum mismatch"),
+            BuildError::InvalidAffixFile => write!(f, "Invalid Hunspell affix file"),
+            BuildError::UnsupportedEncoding(encoding) => {
+                write!(f, "Unsupported dictionary encoding {encoding}")
+            }
+            BuildError::CompressionNotEnabled(feature) => {
+                write!(f, "Compressed input needs the `{feature}` feature")
+            }
+            BuildError::ReaderConsumed => {
+                write!(f, "Dictionary reader was already read by a previous build")
+            }
+            BuildError::InvalidErrorModel(line) => {
+                write!(f, "Error model line {line} has no correction")
+            }
         }
     }
 }
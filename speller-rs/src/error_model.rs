@@ -0,0 +1,250 @@
+use crate::error::BuildError;
+use crate::image::{ImageReader, ImageWriter};
+use crate::keyboard::Keyboard;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Noisy-channel error model: the probability of each edit operation a writer makes when
+/// typing a word, used to estimate P(typo | word).
+///
+/// Operations are seen from the intended word: an insertion adds a character to the typo,
+/// a deletion drops one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorModel {
+    insertion: f64,
+    deletion: f64,
+    substitution: f64,
+    transposition: f64,
+    keyboard: Option<Keyboard>,
+}
+
+impl ErrorModel {
+    /// Every operation has probability 0.001.
+    pub fn new() -> ErrorModel {
+        ErrorModel {
+            insertion: 0.001,
+            deletion: 0.001,
+            substitution: 0.001,
+            transposition: 0.001,
+            keyboard: None,
+        }
+    }
+
+    pub fn with_insertion(mut self, probability: f64) -> ErrorModel {
+        self.insertion = probability;
+        self
+    }
+
+    pub fn with_deletion(mut self, probability: f64) -> ErrorModel {
+        self.deletion = probability;
+        self
+    }
+
+    pub fn with_substitution(mut self, probability: f64) -> ErrorModel {
+        self.substitution = probability;
+        self
+    }
+
+    pub fn with_transposition(mut self, probability: f64) -> ErrorModel {
+        self.transposition = probability;
+        self
+    }
+
+    /// Make substitutions between neighbouring keys more likely. Their cost, `-ln p`, is
+    /// scaled by [`Keyboard::substitution_cost`].
+    pub fn with_keyboard(mut self, keyboard: Keyboard) -> ErrorModel {
+        self.keyboard = Some(keyboard);
+        self
+    }
+
+    pub fn keyboard(&self) -> Option<&Keyboard> {
+        self.keyboard.as_ref()
+    }
+
+    /// Estimate the operation probabilities from `(misspelling, correction)` pairs. Each
+    /// probability is the number of times the operation was seen per character of the
+    /// corrections, with add-one smoothing.
+    pub fn from_pairs<I, S>(pairs: I) -> ErrorModel
+    where
+        I: IntoIterator<Item = (S, S)>,
+        S: AsRef<str>,
+    {
+        let mut counts = [0usize; 4];
+        let mut characters = 0;
+        for (typo, word) in pairs {
+            let typo: Vec<char> = typo.as_ref().chars().collect();
+            let word: Vec<char> = word.as_ref().chars().collect();
+            characters += word.len();
+            for operation in align(&typo, &word) {
+                counts[operation as usize] += 1;
+            }
+        }
+        let probability = |count: usize| (count + 1) as f64 / (characters + 4) as f64;
+        ErrorModel {
+            insertion: probability(counts[Operation::Insertion as usize]),
+            deletion: probability(counts[Operation::Deletion as usize]),
+            substitution: probability(counts[Operation::Substitution as usize]),
+            transposition: probability(counts[Operation::Transposition as usize]),
+            keyboard: None,
+        }
+    }
+
+    /// Learn the model from a reader with one `misspelling correction` pair per line,
+    /// separated by whitespace. Blank lines are skipped.
+    pub fn from_reader<R: io::Read>(reader: R) -> Result<ErrorModel, BuildError> {
+        let mut pairs = vec![];
+        for (number, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let mut parts = line.split_whitespace();
+            let Some(typo) = parts.next() else {
+                continue;
+            };
+            let word = parts
+                .next()
+                .ok_or(BuildError::InvalidErrorModel(number + 1))?;
+            pairs.push((typo.to_string(), word.to_string()));
+        }
+        Ok(ErrorModel::from_pairs(pairs))
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<ErrorModel, BuildError> {
+        ErrorModel::from_reader(File::open(path)?)
+    }
+
+    /// ln P(typo | word): the log-probability of the most likely sequence of operations that
+    /// turns `word` into `typo`. Identical strings have probability 1.
+    pub fn log_probability(&self, typo: &str, word: &str) -> f64 {
+        let typo: Vec<char> = typo.chars().collect();
+        let word: Vec<char> = word.chars().collect();
+        let insertion = -self.insertion.ln();
+        let deletion = -self.deletion.ln();
+        let substitution = -self.substitution.ln();
+        let transposition = -self.transposition.ln();
+
+        // cost[i][j]: cheapest way to turn word[..j] into typo[..i].
+        let mut cost = vec![vec![0.0; word.len() + 1]; typo.len() + 1];
+        for (i, row) in cost.iter_mut().enumerate() {
+            row[0] = i as f64 * insertion;
+        }
+        for (j, cell) in cost[0].iter_mut().enumerate() {
+            *cell = j as f64 * deletion;
+        }
+        for i in 1..=typo.len() {
+            for j in 1..=word.len() {
+                let substitution = match &self.keyboard {
+                    Some(keyboard) => keyboard.substitution_cost(typo[i - 1], word[j - 1]),
+                    None if typo[i - 1] == word[j - 1] => 0.0,
+                    None => 1.0,
+                } * substitution;
+                let mut best = cost[i - 1][j - 1] + substitution;
+                best = best.min(cost[i - 1][j] + insertion);
+                best = best.min(cost[i][j - 1] + deletion);
+                if i > 1 && j > 1 && typo[i - 1] == word[j - 2] && typo[i - 2] == word[j - 1] {
+                    best = best.min(cost[i - 2][j - 2] + transposition);
+                }
+                cost[i][j] = best;
+            }
+        }
+        -cost[typo.len()][word.len()]
+    }
+
+    pub(crate) fn write_image(&self, image: &mut ImageWriter) {
+        image.array([
+            self.insertion,
+            self.deletion,
+            self.substitution,
+            self.transposition,
+        ]);
+        image.flag(self.keyboard.is_some());
+        if let Some(keyboard) = &self.keyboard {
+            keyboard.write_image(image);
+        }
+    }
+
+    pub(crate) fn from_image(image: &mut ImageReader) -> Result<ErrorModel, BuildError> {
+        let probabilities = image.array::<f64>()?;
+        if probabilities.len() != 4 {
+            return Err(BuildError::InvalidImage);
+        }
+        let keyboard = match image.flag()? {
+            true => Some(Keyboard::from_image(image)?),
+            false => None,
+        };
+        Ok(ErrorModel {
+            insertion: probabilities.get(0),
+            deletion: probabilities.get(1),
+            substitution: probabilities.get(2),
+            transposition: probabilities.get(3),
+            keyboard,
+        })
+    }
+}
+
+impl Default for ErrorModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Operation {
+    Insertion,
+    Deletion,
+    Substitution,
+    Transposition,
+}
+
+/// The operations of one optimal alignment turning `word` into `typo`, with unit costs.
+fn align(typo: &[char], word: &[char]) -> Vec<Operation> {
+    let mut distance = vec![vec![0; word.len() + 1]; typo.len() + 1];
+    for (i, row) in distance.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in distance[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=typo.len() {
+        for j in 1..=word.len() {
+            let substitution = usize::from(typo[i - 1] != word[j - 1]);
+            let mut best = (distance[i - 1][j - 1] + substitution)
+                .min(distance[i - 1][j] + 1)
+                .min(distance[i][j - 1] + 1);
+            if i > 1 && j > 1 && typo[i - 1] == word[j - 2] && typo[i - 2] == word[j - 1] {
+                best = best.min(distance[i - 2][j - 2] + 1);
+            }
+            distance[i][j] = best;
+        }
+    }
+
+    let mut operations = vec![];
+    let (mut i, mut j) = (typo.len(), word.len());
+    while i > 0 || j > 0 {
+        let current = distance[i][j];
+        if i > 0 && j > 0 && typo[i - 1] == word[j - 1] && current == distance[i - 1][j - 1] {
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && j > 0 && current == distance[i - 1][j - 1] + 1 {
+            operations.push(Operation::Substitution);
+            i -= 1;
+            j -= 1;
+        } else if i > 1
+            && j > 1
+            && typo[i - 1] == word[j - 2]
+            && typo[i - 2] == word[j - 1]
+            && current == distance[i - 2][j - 2] + 1
+        {
+            operations.push(Operation::Transposition);
+            i -= 2;
+            j -= 2;
+        } else if i > 0 && current == distance[i - 1][j] + 1 {
+            operations.push(Operation::Insertion);
+            i -= 1;
+        } else {
+            operations.push(Operation::Deletion);
+            j -= 1;
+        }
+    }
+    operations
+}
@@ -0,0 +1,516 @@
+//! Hunspell affix files, as used by the `.dic` + `.aff` dictionaries of LibreOffice, Firefox
+//! and most Linux distributions.
+//!
+//! A `.dic` file lists stems, each with the flags of the affix classes it takes, and the
+//! `.aff` file describes those classes: a prefix (`PFX`) or suffix (`SFX`) rule strips some
+//! characters from the stem, adds others, and only applies when the stem matches its
+//! condition. A rule can carry continuation flags of its own, so a suffixed form can take a
+//! second suffix or a prefix.
+
+use crate::compression;
+use crate::error::BuildError;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::io::BufRead;
+use std::path::Path;
+
+/// An affix flag, decoded according to the `FLAG` setting of the affix file.
+pub type Flag = u64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlagType {
+    /// One character per flag, the default.
+    Char,
+    /// Two characters per flag.
+    Long,
+    /// Comma-separated decimal numbers.
+    Num,
+}
+
+/// Encoding of the `.aff` and `.dic` files, from the `SET` setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Utf8,
+    Latin1,
+}
+
+/// One character of an affix condition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CharClass {
+    Any,
+    Set { negated: bool, chars: Vec<char> },
+}
+
+impl CharClass {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            CharClass::Any => true,
+            CharClass::Set { negated, chars } => chars.contains(&c) != *negated,
+        }
+    }
+}
+
+/// A prefix or suffix rule of an affix class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct AffixRule {
+    pub strip: String,
+    pub add: String,
+    condition: Vec<CharClass>,
+    pub continuation: Vec<Flag>,
+}
+
+impl AffixRule {
+    /// Whether the first characters of `word` match the condition, as for a prefix.
+    fn matches_start(&self, word: &str) -> bool {
+        let mut chars = word.chars();
+        self.condition
+            .iter()
+            .all(|class| chars.next().is_some_and(|c| class.matches(c)))
+    }
+
+    /// Whether the last characters of `word` match the condition, as for a suffix.
+    fn matches_end(&self, word: &str) -> bool {
+        let mut chars = word.chars().rev();
+        self.condition
+            .iter()
+            .rev()
+            .all(|class| chars.next().is_some_and(|c| class.matches(c)))
+    }
+
+    /// Apply the rule as a prefix, if `word` matches it.
+    pub fn prefix(&self, word: &str) -> Option<String> {
+        let rest = word.strip_prefix(self.strip.as_str())?;
+        if rest.is_empty() || !self.matches_start(word) {
+            return None;
+        }
+        Some(format!("{}{}", self.add, rest))
+    }
+
+    /// Apply the rule as a suffix, if `word` matches it.
+    pub fn suffix(&self, word: &str) -> Option<String> {
+        let rest = word.strip_suffix(self.strip.as_str())?;
+        if rest.is_empty() || !self.matches_end(word) {
+            return None;
+        }
+        Some(format!("{}{}", rest, self.add))
+    }
+}
+
+/// The rules sharing a flag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct AffixClass {
+    /// Whether the rules combine with affixes of the other kind.
+    pub cross_product: bool,
+    pub rules: Vec<AffixRule>,
+}
+
+/// A form derived from a stem by its suffixes, before any prefix.
+struct Derived {
+    word: String,
+    cross_product: bool,
+    continuation: Vec<Flag>,
+}
+
+/// The affix rules and settings of a Hunspell `.aff` file.
+///
+/// Only the settings that change which words are valid are read: `SET`, `FLAG`, `AF`,
+/// `IGNORE`, `NEEDAFFIX`, `FORBIDDENWORD`, `ONLYINCOMPOUND`, `CIRCUMFIX` and the `PFX` and
+/// `SFX` classes. Compounding rules and suggestion settings are ignored. Encodings other than
+/// UTF-8 and ISO8859-1 are rejected with [`BuildError::UnsupportedEncoding`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Affixes {
+    flag_type: FlagType,
+    encoding: Encoding,
+    aliases: Vec<Vec<Flag>>,
+    ignore: Vec<char>,
+    need_affix: Option<Flag>,
+    forbidden: Option<Flag>,
+    only_in_compound: Option<Flag>,
+    circumfix: Option<Flag>,
+    pub(crate) prefixes: HashMap<Flag, AffixClass>,
+    pub(crate) suffixes: HashMap<Flag, AffixClass>,
+}
+
+impl Affixes {
+    pub fn from_reader<R: io::Read>(mut reader: R) -> Result<Affixes, BuildError> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        // `SET` is ASCII, so it can be read before the encoding is known.
+        let encoding = String::from_utf8_lossy(&bytes)
+            .lines()
+            .find_map(|line| line.strip_prefix("SET").map(|set| set.trim().to_string()));
+        let encoding = match encoding.as_deref() {
+            Some("UTF-8") | Some("utf-8") => Encoding::Utf8,
+            Some("ISO8859-1") | Some("ISO-8859-1") => Encoding::Latin1,
+            Some(other) => return Err(BuildError::UnsupportedEncoding(other.to_string())),
+            // The Hunspell default is ISO8859-1, but files without `SET` are usually UTF-8.
+            None if std::str::from_utf8(&bytes).is_ok() => Encoding::Utf8,
+            None => Encoding::Latin1,
+        };
+        let mut affixes = Affixes {
+            flag_type: FlagType::Char,
+            encoding,
+            aliases: vec![],
+            ignore: vec![],
+            need_affix: None,
+            forbidden: None,
+            only_in_compound: None,
+            circumfix: None,
+            prefixes: HashMap::new(),
+            suffixes: HashMap::new(),
+        };
+        let text = affixes.decode(&bytes)?;
+        // Rules still expected for the class whose header was read last.
+        let mut pending = 0;
+        let mut aliases_header = false;
+        for line in text.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let Some(&keyword) = fields.first() else {
+                continue;
+            };
+            match keyword {
+                "FLAG" => {
+                    affixes.flag_type = match fields.get(1) {
+                        Some(&"long") => FlagType::Long,
+                        Some(&"num") => FlagType::Num,
+                        // UTF-8 flags are single characters, like the default.
+                        _ => FlagType::Char,
+                    }
+                }
+                // The first `AF` line gives the number of aliases.
+                "AF" if !aliases_header => aliases_header = true,
+                "AF" => {
+                    let flags = affixes.decode_flags(fields.get(1).unwrap_or(&""))?;
+                    affixes.aliases.push(flags);
+                }
+                "IGNORE" => {
+                    affixes.ignore = fields.get(1).map_or(vec![], |s| s.chars().collect());
+                }
+                "NEEDAFFIX" | "PSEUDOROOT" => affixes.need_affix = affixes.setting(&fields)?,
+                "FORBIDDENWORD" => affixes.forbidden = affixes.setting(&fields)?,
+                "ONLYINCOMPOUND" => affixes.only_in_compound = affixes.setting(&fields)?,
+                "CIRCUMFIX" => affixes.circumfix = affixes.setting(&fields)?,
+                "PFX" | "SFX" => {
+                    if fields.len() < 4 {
+                        return Err(BuildError::InvalidAffixFile);
+                    }
+                    let flag = affixes.parse_flag(fields[1])?;
+                    let classes = match keyword {
+                        "PFX" => &mut affixes.prefixes,
+                        _ => &mut affixes.suffixes,
+                    };
+                    if pending == 0 {
+                        pending = fields[3]
+                            .parse()
+                            .map_err(|_| BuildError::InvalidAffixFile)?;
+                        classes.insert(
+                            flag,
+                            AffixClass {
+                                cross_product: fields[2] == "Y",
+                                rules: vec![],
+                            },
+                        );
+                        continue;
+                    }
+                    pending -= 1;
+                    let (add, continuation) = match fields[3].split_once('/') {
+                        Some((add, flags)) => (add, flags.to_string()),
+                        None => (fields[3], String::new()),
+                    };
+                    let rule = AffixRule {
+                        strip: none_if_zero(fields[2]).to_string(),
+                        add: none_if_zero(add)
+                            .chars()
+                            .filter(|c| !affixes.ignore.contains(c))
+                            .collect(),
+                        condition: parse_condition(fields.get(4).copied().unwrap_or("."))?,
+                        continuation: affixes.parse_flags(&continuation)?,
+                    };
+                    let classes = match keyword {
+                        "PFX" => &mut affixes.prefixes,
+                        _ => &mut affixes.suffixes,
+                    };
+                    classes
+                        .get_mut(&flag)
+                        .ok_or(BuildError::InvalidAffixFile)?
+                        .rules
+                        .push(rule);
+                }
+                _ => {}
+            }
+        }
+        Ok(affixes)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Affixes, BuildError> {
+        Affixes::from_reader(compression::open(path)?)
+    }
+
+    /// Decode the bytes of the `.aff` or `.dic` file with the encoding set by the `.aff` file.
+    pub fn decode(&self, bytes: &[u8]) -> Result<String, BuildError> {
+        match self.encoding {
+            Encoding::Utf8 => String::from_utf8(bytes.to_vec())
+                .map_err(|_| BuildError::UnsupportedEncoding("UTF-8".to_string())),
+            Encoding::Latin1 => Ok(bytes.iter().map(|&b| b as char).collect()),
+        }
+    }
+
+    /// Split a line of a `.dic` file into its stem and flags. Returns `None` for the word
+    /// count on the first line, blank lines and comments.
+    pub fn parse_entry(&self, line: &str) -> Result<Option<(String, Vec<Flag>)>, BuildError> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with(['#', '\t']) || line.parse::<usize>().is_ok() {
+            return Ok(None);
+        }
+        // A slash escaped with a backslash is part of the word.
+        let mut word = String::new();
+        let mut flags = "";
+        let mut chars = line.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some((_, '/')) => word.push('/'),
+                    Some((_, c)) => {
+                        word.push('\\');
+                        word.push(c);
+                    }
+                    None => word.push('\\'),
+                },
+                '/' => {
+                    let rest = &line[i + 1..];
+                    flags = rest.split_whitespace().next().unwrap_or("");
+                    break;
+                }
+                c if c.is_whitespace() => break,
+                c => word.push(c),
+            }
+        }
+        word.retain(|c| !self.ignore.contains(&c));
+        if word.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some((word, self.parse_flags(flags)?)))
+    }
+
+    /// Call `entry` with the stem and flags of every line of a `.dic` file.
+    pub fn read_dic<R, F>(&self, reader: R, mut entry: F) -> Result<(), BuildError>
+    where
+        R: io::Read,
+        F: FnMut(String, Vec<Flag>),
+    {
+        let mut reader = io::BufReader::new(reader);
+        let mut line = vec![];
+        while reader.read_until(b'\n', &mut line)? > 0 {
+            let text = self.decode(&line)?;
+            line.clear();
+            if let Some((stem, flags)) = self.parse_entry(&text)? {
+                entry(stem, flags);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a stem with `flags` is forbidden.
+    pub fn is_forbidden(&self, flags: &[Flag]) -> bool {
+        has(flags, self.forbidden)
+    }
+
+    /// Whether a stem with `flags` is a word on its own, without any affix.
+    pub(crate) fn is_standalone(&self, flags: &[Flag]) -> bool {
+        !has(flags, self.forbidden)
+            && !has(flags, self.only_in_compound)
+            && !has(flags, self.need_affix)
+    }
+
+    /// Every surface form of a stem with `flags`: the stem itself unless it needs an affix,
+    /// and the forms derived from it by its prefix and suffix classes and their continuation
+    /// classes.
+    pub fn expand(&self, stem: &str, flags: &[Flag]) -> Vec<String> {
+        let mut forms = vec![];
+        if has(flags, self.forbidden) || has(flags, self.only_in_compound) {
+            return forms;
+        }
+        if !has(flags, self.need_affix) {
+            forms.push(stem.to_string());
+        }
+
+        // Suffixes, each followed by at most one more suffix from its continuation classes.
+        let mut derived = vec![];
+        for (class, rule) in self.rules(&self.suffixes, flags) {
+            let Some(word) = rule.suffix(stem) else {
+                continue;
+            };
+            for (inner_class, inner) in self.rules(&self.suffixes, &rule.continuation) {
+                if let Some(word) = inner.suffix(&word) {
+                    derived.push(Derived {
+                        word,
+                        cross_product: class.cross_product && inner_class.cross_product,
+                        continuation: [rule.continuation.as_slice(), &inner.continuation].concat(),
+                    });
+                }
+            }
+            derived.push(Derived {
+                word,
+                cross_product: class.cross_product,
+                continuation: rule.continuation.clone(),
+            });
+        }
+        for form in derived.iter() {
+            if !has(&form.continuation, self.need_affix) && !has(&form.continuation, self.circumfix)
+            {
+                forms.push(form.word.clone());
+            }
+        }
+
+        // Prefixes, on the stem and on the suffixed forms that allow it.
+        for (class, rule) in self.rules(&self.prefixes, flags) {
+            let Some(word) = rule.prefix(stem) else {
+                continue;
+            };
+            if !has(&rule.continuation, self.need_affix) && !has(&rule.continuation, self.circumfix)
+            {
+                forms.push(word.clone());
+            }
+            // Suffixes only allowed after this prefix.
+            for (inner_class, inner) in self.rules(&self.suffixes, &rule.continuation) {
+                if !class.cross_product || !inner_class.cross_product {
+                    continue;
+                }
+                if let Some(word) = inner.suffix(&word) {
+                    forms.push(word);
+                }
+            }
+        }
+        for form in derived.iter().filter(|form| form.cross_product) {
+            let prefix_flags = [flags, &form.continuation].concat();
+            for (class, rule) in self.rules(&self.prefixes, &prefix_flags) {
+                // A circumfix is a prefix and a suffix that only come together.
+                if !class.cross_product
+                    || has(&rule.continuation, self.circumfix)
+                        != has(&form.continuation, self.circumfix)
+                {
+                    continue;
+                }
+                if let Some(word) = rule.prefix(&form.word) {
+                    forms.push(word);
+                }
+            }
+        }
+
+        let mut seen = HashSet::new();
+        forms.retain(|form| seen.insert(form.clone()));
+        forms
+    }
+
+    /// The rules of the classes of `flags`, with their class.
+    pub(crate) fn rules<'a>(
+        &'a self,
+        classes: &'a HashMap<Flag, AffixClass>,
+        flags: &'a [Flag],
+    ) -> impl Iterator<Item = (&'a AffixClass, &'a AffixRule)> + 'a {
+        flags
+            .iter()
+            .filter_map(|flag| classes.get(flag))
+            .flat_map(|class| class.rules.iter().map(move |rule| (class, rule)))
+    }
+
+    fn setting(&self, fields: &[&str]) -> Result<Option<Flag>, BuildError> {
+        match fields.get(1) {
+            Some(flag) => Ok(Some(self.parse_flag(flag)?)),
+            None => Err(BuildError::InvalidAffixFile),
+        }
+    }
+
+    fn parse_flag(&self, flag: &str) -> Result<Flag, BuildError> {
+        match self.decode_flags(flag)?.as_slice() {
+            [flag] => Ok(*flag),
+            _ => Err(BuildError::InvalidAffixFile),
+        }
+    }
+
+    /// Decode a string of flags, or the number of an `AF` alias.
+    fn parse_flags(&self, flags: &str) -> Result<Vec<Flag>, BuildError> {
+        if !self.aliases.is_empty() {
+            if let Ok(alias) = flags.parse::<usize>() {
+                return alias
+                    .checked_sub(1)
+                    .and_then(|i| self.aliases.get(i))
+                    .cloned()
+                    .ok_or(BuildError::InvalidAffixFile);
+            }
+        }
+        self.decode_flags(flags)
+    }
+
+    fn decode_flags(&self, flags: &str) -> Result<Vec<Flag>, BuildError> {
+        match self.flag_type {
+            FlagType::Char => Ok(flags.chars().map(|c| c as Flag).collect()),
+            FlagType::Long => {
+                let chars: Vec<char> = flags.chars().collect();
+                chars
+                    .chunks(2)
+                    .map(|pair| match pair {
+                        [a, b] => Ok(((*a as Flag) << 32) | *b as Flag),
+                        _ => Err(BuildError::InvalidAffixFile),
+                    })
+                    .collect()
+            }
+            FlagType::Num => flags
+                .split(',')
+                .filter(|flag| !flag.is_empty())
+                .map(|flag| flag.parse().map_err(|_| BuildError::InvalidAffixFile))
+                .collect(),
+        }
+    }
+}
+
+fn has(flags: &[Flag], flag: Option<Flag>) -> bool {
+    flag.is_some_and(|flag| flags.contains(&flag))
+}
+
+/// `0` stands for an empty strip or affix.
+fn none_if_zero(field: &str) -> &str {
+    match field {
+        "0" => "",
+        field => field,
+    }
+}
+
+/// Parse a condition such as `[^aeiou]y`: literal characters, `.` for any character and
+/// bracketed sets.
+fn parse_condition(condition: &str) -> Result<Vec<CharClass>, BuildError> {
+    if condition == "." {
+        return Ok(vec![]);
+    }
+    let mut classes = vec![];
+    let mut chars = condition.chars();
+    while let Some(c) = chars.next() {
+        let class = match c {
+            '.' => CharClass::Any,
+            '[' => {
+                let mut set: Vec<char> = vec![];
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        Some(c) => set.push(c),
+                        None => return Err(BuildError::InvalidAffixFile),
+                    }
+                }
+                let negated = set.first() == Some(&'^');
+                if negated {
+                    set.remove(0);
+                }
+                CharClass::Set {
+                    negated,
+                    chars: set,
+                }
+            }
+            c => CharClass::Set {
+                negated: false,
+                chars: vec![c],
+            },
+        };
+        classes.push(class);
+    }
+    Ok(classes)
+}
@@ -0,0 +1,286 @@
+//! Binary image of a built speller, written by [`Speller::save`](crate::Speller::save).
+//!
+//! An image starts with a 16-byte header: the magic bytes, the format version and the CRC-32
+//! of the body. The body is a sequence of sections, each a little-endian `u64` length
+//! followed by that many bytes, padded to a multiple of 8. Sections hold flat arrays of
+//! little-endian numbers or UTF-8 bytes, so they can be read in place from a memory map.
+
+use crate::error::BuildError;
+use std::cmp::Ordering;
+use std::io;
+use std::io::Write;
+use std::marker::PhantomData;
+
+pub(crate) const MAGIC: &[u8; 8] = b"SPELLER\0";
+
+/// Bumped whenever the layout of the sections changes.
+pub(crate) const VERSION: u32 = 1;
+
+const HEADER_LEN: usize = 16;
+
+/// Number types stored in sections.
+pub(crate) trait Element: Copy {
+    const SIZE: usize;
+    fn read(bytes: &[u8]) -> Self;
+    fn write(self, out: &mut Vec<u8>);
+}
+
+macro_rules! element {
+    ($($t:ty),*) => {$(
+        impl Element for $t {
+            const SIZE: usize = std::mem::size_of::<$t>();
+
+            fn read(bytes: &[u8]) -> Self {
+                <$t>::from_le_bytes(bytes.try_into().unwrap())
+            }
+
+            fn write(self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+        }
+    )*};
+}
+
+element!(u8, u32, i32, u64, i64, f64);
+
+/// Collects the sections of an image and writes them with their header.
+pub(crate) struct ImageWriter {
+    body: Vec<u8>,
+}
+
+impl ImageWriter {
+    pub fn new() -> ImageWriter {
+        ImageWriter { body: vec![] }
+    }
+
+    fn section(&mut self, bytes: &[u8]) {
+        self.body
+            .extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+        self.body.extend_from_slice(bytes);
+        let padding = (8 - bytes.len() % 8) % 8;
+        self.body.resize(self.body.len() + padding, 0);
+    }
+
+    pub fn array<T: Element, I: IntoIterator<Item = T>>(&mut self, values: I) {
+        let mut bytes = vec![];
+        for value in values {
+            value.write(&mut bytes);
+        }
+        self.section(&bytes);
+    }
+
+    /// Write `strings` as two sections: the `u32` offset of each string followed by the end
+    /// of the last one, then the bytes of all the strings.
+    pub fn strings<S: AsRef<str>, I: IntoIterator<Item = S>>(&mut self, strings: I) {
+        let mut offsets = vec![0u32];
+        let mut bytes = vec![];
+        for string in strings {
+            bytes.extend_from_slice(string.as_ref().as_bytes());
+            offsets.push(bytes.len() as u32);
+        }
+        self.array(offsets);
+        self.section(&bytes);
+    }
+
+    pub fn flag(&mut self, value: bool) {
+        self.array([value as u32]);
+    }
+
+    pub fn finish<W: Write>(self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&crc32fast::hash(&self.body).to_le_bytes())?;
+        writer.write_all(&self.body)?;
+        writer.flush()
+    }
+}
+
+/// Reads the sections of an image in the order they were written.
+pub(crate) struct ImageReader<'a> {
+    image: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ImageReader<'a> {
+    /// Check the header of `bytes` and the checksum of its body.
+    pub fn new(bytes: &'a [u8]) -> Result<ImageReader<'a>, BuildError> {
+        if bytes.len() < HEADER_LEN || &bytes[..8] != MAGIC {
+            return Err(BuildError::InvalidImage);
+        }
+        let version = <u32 as Element>::read(&bytes[8..12]);
+        if version != VERSION {
+            return Err(BuildError::UnsupportedImageVersion(version));
+        }
+        if <u32 as Element>::read(&bytes[12..16]) != crc32fast::hash(&bytes[HEADER_LEN..]) {
+            return Err(BuildError::ChecksumMismatch);
+        }
+        Ok(ImageReader {
+            image: bytes,
+            position: HEADER_LEN,
+        })
+    }
+
+    pub fn array<T: Element>(&mut self) -> Result<Array<'a, T>, BuildError> {
+        let start = self.position + 8;
+        let length = self
+            .image
+            .get(self.position..start)
+            .map(<u64 as Element>::read)
+            .ok_or(BuildError::InvalidImage)? as usize;
+        let end = start.checked_add(length).ok_or(BuildError::InvalidImage)?;
+        if end > self.image.len() || !length.is_multiple_of(T::SIZE) {
+            return Err(BuildError::InvalidImage);
+        }
+        self.position = end + (8 - length % 8) % 8;
+        Ok(Array {
+            image: self.image,
+            start,
+            end,
+            element: PhantomData,
+        })
+    }
+
+    pub fn strings(&mut self) -> Result<Strings<'a>, BuildError> {
+        let offsets: Array<u32> = self.array()?;
+        let bytes: Array<u8> = self.array()?;
+        if offsets.is_empty() {
+            return Err(BuildError::InvalidImage);
+        }
+        let mut previous = 0;
+        for offset in offsets.iter() {
+            if offset < previous || offset as usize > bytes.len() {
+                return Err(BuildError::InvalidImage);
+            }
+            previous = offset;
+        }
+        let strings = Strings { offsets, bytes };
+        // Every string must be valid UTF-8 on its own.
+        for i in 0..strings.len() {
+            strings.try_get(i).ok_or(BuildError::InvalidImage)?;
+        }
+        Ok(strings)
+    }
+
+    pub fn flag(&mut self) -> Result<bool, BuildError> {
+        let flag: Array<u32> = self.array()?;
+        match flag.len() {
+            1 => Ok(flag.get(0) != 0),
+            _ => Err(BuildError::InvalidImage),
+        }
+    }
+}
+
+/// A section read as an array of numbers, in place.
+///
+/// An array remembers where its section is in the image, so it can be
+/// [rebound](Array::rebind) to another copy of it, such as a memory map kept alongside.
+pub(crate) struct Array<'a, T> {
+    image: &'a [u8],
+    start: usize,
+    end: usize,
+    element: PhantomData<T>,
+}
+
+impl<T> Clone for Array<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Array<'_, T> {}
+
+impl<'a, T: Element + 'a> Array<'a, T> {
+    fn bytes(&self) -> &'a [u8] {
+        &self.image[self.start..self.end]
+    }
+
+    pub fn len(&self) -> usize {
+        (self.end - self.start) / T::SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn get(&self, i: usize) -> T {
+        let start = self.start + i * T::SIZE;
+        T::read(&self.image[start..start + T::SIZE])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = T> + 'a {
+        self.bytes().chunks_exact(T::SIZE).map(T::read)
+    }
+
+    /// Index of the first element for which `predicate` is false, in an array partitioned by
+    /// it, as with [`slice::partition_point`].
+    pub fn partition_point<P: Fn(T) -> bool>(&self, predicate: P) -> usize {
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let middle = (low + high) / 2;
+            if predicate(self.get(middle)) {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        low
+    }
+
+    /// The same section in `image`, which must be a copy of the image it was read from.
+    pub fn rebind<'b>(&self, image: &'b [u8]) -> Array<'b, T> {
+        Array {
+            image,
+            start: self.start,
+            end: self.end,
+            element: PhantomData,
+        }
+    }
+}
+
+/// A pair of sections read as an array of strings, in place.
+#[derive(Clone, Copy)]
+pub(crate) struct Strings<'a> {
+    offsets: Array<'a, u32>,
+    bytes: Array<'a, u8>,
+}
+
+impl<'a> Strings<'a> {
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    fn try_get(&self, i: usize) -> Option<&'a str> {
+        let start = self.offsets.get(i) as usize;
+        let end = self.offsets.get(i + 1) as usize;
+        std::str::from_utf8(&self.bytes.bytes()[start..end]).ok()
+    }
+
+    pub fn get(&self, i: usize) -> &'a str {
+        self.try_get(i).unwrap_or_default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'a str> + '_ {
+        (0..self.len()).map(|i| self.get(i))
+    }
+
+    /// Index of `key` in strings sorted in ascending order.
+    pub fn binary_search(&self, key: &str) -> Option<usize> {
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let middle = (low + high) / 2;
+            match self.get(middle).cmp(key) {
+                Ordering::Less => low = middle + 1,
+                Ordering::Greater => high = middle,
+                Ordering::Equal => return Some(middle),
+            }
+        }
+        None
+    }
+
+    pub fn rebind<'b>(&self, image: &'b [u8]) -> Strings<'b> {
+        Strings {
+            offsets: self.offsets.rebind(image),
+            bytes: self.bytes.rebind(image),
+        }
+    }
+}
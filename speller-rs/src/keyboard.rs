@@ -0,0 +1,124 @@
+use crate::error::BuildError;
+use crate::image::{ImageReader, ImageWriter};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Cost of substituting a key with one of its neighbours, instead of 1.
+pub const ADJACENT_SUBSTITUTION_COST: f64 = 0.5;
+
+/// Physical key positions of a keyboard layout, used to tell neighbouring keys apart from
+/// distant ones. Rows are staggered by half a key, as on a standard keyboard.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyboard {
+    keys: HashMap<char, (usize, f64)>,
+}
+
+impl Keyboard {
+    /// Build a layout from its rows, top to bottom, each listing its keys left to right.
+    pub fn from_rows<S: AsRef<str>>(rows: &[S]) -> Keyboard {
+        let mut keys = HashMap::new();
+        for (row, keys_in_row) in rows.iter().enumerate() {
+            let keys_in_row = keys_in_row.as_ref().chars().filter(|c| !c.is_whitespace());
+            for (column, key) in keys_in_row.enumerate() {
+                let x = column as f64 + row as f64 * 0.5;
+                for key in key.to_lowercase() {
+                    keys.entry(key).or_insert((row, x));
+                }
+            }
+        }
+        Keyboard { keys }
+    }
+
+    pub fn qwerty() -> Keyboard {
+        Keyboard::from_rows(&["1234567890-=", "qwertyuiop[]", "asdfghjkl;'", "zxcvbnm,./"])
+    }
+
+    pub fn azerty() -> Keyboard {
+        Keyboard::from_rows(&[
+            "&é\"'(-è_çà)=",
+            "azertyuiop^$",
+            "qsdfghjklmù*",
+            "wxcvbn,;:!",
+        ])
+    }
+
+    pub fn qwertz() -> Keyboard {
+        Keyboard::from_rows(&["1234567890ß´", "qwertzuiopü+", "asdfghjklöä#", "yxcvbnm,.-"])
+    }
+
+    pub fn dvorak() -> Keyboard {
+        Keyboard::from_rows(&["1234567890[]", "',.pyfgcrl/=", "aoeuidhtns-", ";qjkxbmwvz"])
+    }
+
+    /// Read a layout with one keyboard row per line, top to bottom. Whitespace inside a row is
+    /// ignored, as are blank lines and lines starting with `#`.
+    pub fn from_reader<R: io::Read>(reader: R) -> Result<Keyboard, BuildError> {
+        let mut rows = vec![];
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let line = line.trim();
+            if !line.is_empty() && !line.starts_with('#') {
+                rows.push(line.to_string());
+            }
+        }
+        Ok(Keyboard::from_rows(&rows))
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Keyboard, BuildError> {
+        Keyboard::from_reader(File::open(path)?)
+    }
+
+    /// Whether `a` and `b` are different keys next to each other, ignoring case.
+    pub fn is_adjacent(&self, a: char, b: char) -> bool {
+        let key = |c: char| c.to_lowercase().next().and_then(|c| self.keys.get(&c));
+        match (key(a), key(b)) {
+            (Some(&(row_a, x_a)), Some(&(row_b, x_b))) => {
+                row_a.abs_diff(row_b) <= 1
+                    && (x_a - x_b).abs() <= 1.0
+                    && (row_a, x_a) != (row_b, x_b)
+            }
+            _ => false,
+        }
+    }
+
+    /// Cost of typing `a` instead of `b`: 0 for the same character, less than 1 for
+    /// neighbouring keys and 1 otherwise.
+    pub fn substitution_cost(&self, a: char, b: char) -> f64 {
+        if a == b {
+            0.0
+        } else if self.is_adjacent(a, b) {
+            ADJACENT_SUBSTITUTION_COST
+        } else {
+            1.0
+        }
+    }
+
+    /// Write the keys, sorted, followed by their row and horizontal position.
+    pub(crate) fn write_image(&self, image: &mut ImageWriter) {
+        let mut keys: Vec<(&char, &(usize, f64))> = self.keys.iter().collect();
+        keys.sort_by_key(|&(key, _)| *key);
+        image.array(keys.iter().map(|(&key, _)| key as u32));
+        image.array(keys.iter().map(|(_, &(row, _))| row as u32));
+        image.array(keys.iter().map(|(_, &(_, x))| x));
+    }
+
+    pub(crate) fn from_image(image: &mut ImageReader) -> Result<Keyboard, BuildError> {
+        let keys = image.array::<u32>()?;
+        let rows = image.array::<u32>()?;
+        let xs = image.array::<f64>()?;
+        if keys.len() != rows.len() || keys.len() != xs.len() {
+            return Err(BuildError::InvalidImage);
+        }
+        let mut keyboard = Keyboard {
+            keys: HashMap::with_capacity(keys.len()),
+        };
+        for ((key, row), x) in keys.iter().zip(rows.iter()).zip(xs.iter()) {
+            let key = char::from_u32(key).ok_or(BuildError::InvalidImage)?;
+            keyboard.keys.insert(key, (row as usize, x));
+        }
+        Ok(keyboard)
+    }
+}
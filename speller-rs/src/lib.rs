@@ -1,28 +1,250 @@
+pub mod code;
+pub mod compression;
 pub mod error;
+pub mod error_model;
+pub mod hunspell;
+mod image;
+pub mod keyboard;
+mod mapped;
+pub mod morphology;
+mod ngram;
 pub mod source;
 mod string;
+mod symspell;
+pub mod tokenize;
+mod trie;
+pub mod user;
 
+use crate::code::Language;
+use crate::compression::Compression;
 use crate::error::BuildError;
+use crate::error_model::ErrorModel;
+use crate::hunspell::Affixes;
+use crate::image::ImageWriter;
+use crate::keyboard::Keyboard;
+use crate::mapped::{DictionaryView, ImageView, MappedDictionary, Settings};
+use crate::morphology::Morphology;
+use crate::ngram::NgramModel;
 #[cfg(feature = "csv")]
 use crate::source::CsvWordLoader;
 #[cfg(feature = "serde_json")]
 use crate::source::JsonWordLoader;
-use crate::source::{TextWordLoader, WordLoader};
-use crate::string::PUNCTUATION;
-use levenshtein_automata::{Distance, LevenshteinAutomatonBuilder};
+use crate::source::{
+    Cutoff, Format, HunspellWordLoader, StreamingWordLoader, TextWordLoader, WordLoader,
+};
+use crate::string::{CasePattern, PUNCTUATION};
+use crate::symspell::DeleteIndex;
+use crate::tokenize::{tokenize, TokenKind};
+use crate::trie::Trie;
+use crate::user::UserDictionary;
+use levenshtein_automata::LevenshteinAutomatonBuilder;
+use std::borrow::Cow;
+use std::sync::Mutex;
 use std::{cmp, mem};
 
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::ffi::OsStr;
 use std::fs::File;
-use std::io::BufReader;
-use std::path::Path;
+use std::io;
+use std::io::{BufReader, BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+/// A spell checker over a base dictionary and a stack of [`UserDictionary`] layers.
+///
+/// The base dictionary and its index are shared between clones, so a speller built once can
+/// be cloned cheaply and given its own layers, for example one per user. Changing the base
+/// with [`Speller::add_word`] and friends copies it first if it is shared or memory-mapped.
+#[derive(Clone)]
 pub struct Speller {
     distance: i32,
     case_sensitive: bool,
-    automaton_builder: LevenshteinAutomatonBuilder,
-    word_frequency: WordFrequency,
+    automaton_builder: Arc<LevenshteinAutomatonBuilder>,
+    base: Base,
+    layers: Vec<UserDictionary>,
+    morphology: Vec<Arc<Morphology>>,
+    error_model: Option<ErrorModel>,
+    keyboard: Option<Keyboard>,
+    restore_case: bool,
+}
+
+/// Lookup structure used to find dictionary words close to a query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// Walk a trie of the dictionary with a Levenshtein automaton. Compact, and fast at any distance.
+    #[default]
+    Automaton,
+    /// Precompute the delete-neighbourhood of every word (SymSpell). Uses much more memory
+    /// and takes longer to build, but answers lookups at distance 1-2 with hash lookups.
+    SymmetricDelete,
+}
+
+#[derive(Clone)]
+enum Index {
+    Automaton(Trie),
+    SymmetricDelete(DeleteIndex),
+}
+
+impl Index {
+    fn insert(&mut self, word: &str, id: u32) {
+        match self {
+            Index::Automaton(trie) => trie.insert(word, id),
+            Index::SymmetricDelete(index) => index.insert(word, id),
+        }
+    }
+
+    fn remove(&mut self, word: &str, id: u32) {
+        match self {
+            Index::Automaton(trie) => trie.remove(word),
+            Index::SymmetricDelete(index) => index.remove(word, id),
+        }
+    }
+
+    fn relabel(&mut self, word: &str, from: u32, to: u32) {
+        match self {
+            Index::Automaton(trie) => trie.insert(word, to),
+            Index::SymmetricDelete(index) => index.relabel(word, from, to),
+        }
+    }
+}
+
+/// The dictionary and index under the user layers: built in memory, or used in place from a
+/// memory-mapped image.
+#[derive(Clone)]
+enum Base {
+    Owned(Arc<WordFrequency>, Arc<Index>),
+    Mapped(Arc<MappedDictionary>),
+}
+
+impl Base {
+    fn count(&self, key: &str) -> Option<i32> {
+        match self {
+            Base::Owned(word_frequency, _) => word_frequency.dictionary.get(key).copied(),
+            Base::Mapped(mapped) => mapped.count(key),
+        }
+    }
+
+    /// The word with id `id`.
+    fn word(&self, id: u32) -> &str {
+        match self {
+            Base::Owned(word_frequency, _) => &word_frequency.list[id as usize],
+            Base::Mapped(mapped) => mapped.word(id),
+        }
+    }
+
+    fn cased(&self, key: &str) -> Option<&str> {
+        match self {
+            Base::Owned(word_frequency, _) => word_frequency.cased.get(key).map(String::as_str),
+            Base::Mapped(mapped) => mapped.cased(key),
+        }
+    }
+
+    fn total_count(&self) -> i64 {
+        match self {
+            Base::Owned(word_frequency, _) => word_frequency.total_count,
+            Base::Mapped(mapped) => mapped.total_count(),
+        }
+    }
+
+    fn longest_word(&self) -> usize {
+        match self {
+            Base::Owned(word_frequency, _) => word_frequency.longest_word,
+            Base::Mapped(mapped) => mapped.longest_word(),
+        }
+    }
+
+    fn ngram_score<F>(&self, context: &[&str], word: &str, unigram: F, total: i64) -> f64
+    where
+        F: Fn(&str) -> i64,
+    {
+        match self {
+            Base::Owned(word_frequency, _) => {
+                word_frequency.ngrams.score(context, word, unigram, total)
+            }
+            Base::Mapped(mapped) => mapped.ngram_score(context, word, unigram, total),
+        }
+    }
+
+    /// Return the id and the distance of every word within `distance` of `word`, in no
+    /// particular order.
+    fn lookup(
+        &self,
+        builder: &LevenshteinAutomatonBuilder,
+        word: &str,
+        distance: u8,
+    ) -> Vec<(u32, u8)> {
+        match self {
+            Base::Owned(word_frequency, index) => match index.as_ref() {
+                Index::Automaton(trie) => {
+                    let mut matches = trie.search(&builder.build_dfa(word));
+                    matches.retain(|&(_, d)| d <= distance);
+                    matches
+                }
+                Index::SymmetricDelete(index) => index.search(word, distance as usize, |id| {
+                    word_frequency.list[id as usize].as_str()
+                }),
+            },
+            Base::Mapped(mapped) => mapped.lookup(builder, word, distance),
+        }
+    }
+
+    /// The dictionary and index, to modify them. They are copied first if they are shared
+    /// with another speller or mapped.
+    fn to_mut(&mut self, case_sensitive: bool) -> (&mut WordFrequency, &mut Index) {
+        if let Base::Mapped(mapped) = self {
+            let (word_frequency, index) = mapped.materialize(case_sensitive);
+            *self = Base::Owned(Arc::new(word_frequency), Arc::new(index));
+        }
+        match self {
+            Base::Owned(word_frequency, index) => {
+                (Arc::make_mut(word_frequency), Arc::make_mut(index))
+            }
+            Base::Mapped(_) => unreachable!(),
+        }
+    }
+}
+
+/// Number of suggestions attached to each [`Misspelling`].
+pub const MAX_SUGGESTIONS: usize = 5;
+
+/// Probability given to a single edit when distance and frequency are combined into a score.
+const EDIT_PROBABILITY: f64 = 0.001;
+
+/// Probability that a known word was typed as intended, when looking for real-word errors.
+const KEEP_PROBABILITY: f64 = 0.95;
+
+/// A candidate correction together with the values that ranked it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub word: String,
+    pub distance: u8,
+    pub count: i32,
+    /// Log-probability of the candidate, `ln P(word) + ln P(typo | word)`. Without an
+    /// [`ErrorModel`], every edit has probability 0.001. Higher is better.
+    pub score: f64,
+}
+
+/// A misspelled word found by [`Speller::check_text`], with its position in the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Misspelling {
+    pub word: String,
+    pub byte_span: Range<usize>,
+    pub char_span: Range<usize>,
+    pub suggestions: Vec<String>,
+}
+
+impl Misspelling {
+    /// A misspelling without suggestions.
+    fn new(word: &str, byte_span: Range<usize>, char_span: Range<usize>) -> Misspelling {
+        Misspelling {
+            word: word.to_string(),
+            byte_span,
+            char_span,
+            suggestions: vec![],
+        }
+    }
 }
 
 impl Speller {
@@ -31,12 +253,48 @@ impl Speller {
     }
 
     fn known(&self, word: &str) -> bool {
-        let word = if self.case_sensitive {
+        self.count_of(&self.normalize(word)).is_some()
+    }
+
+    /// The key `word` is looked up with: the word itself in a case-sensitive speller, its
+    /// lowercase form otherwise.
+    pub fn normalize(&self, word: &str) -> String {
+        if self.case_sensitive {
             word.to_string()
         } else {
             word.to_lowercase()
-        };
-        self.word_frequency.dictionary.contains_key(&word)
+        }
+    }
+
+    fn count(&self, word: &str) -> i32 {
+        self.count_of(&self.normalize(word)).unwrap_or(0)
+    }
+
+    /// The count of the normalized `key` as seen through the layers, from the top one down to
+    /// the base dictionary, then the inflected forms of the morphology. `None` if the word is
+    /// unknown or banned.
+    fn count_of(&self, key: &str) -> Option<i32> {
+        match self.layers.iter().rev().find_map(|layer| layer.entry(key)) {
+            Some(entry) => entry,
+            None => self.base.count(key).or_else(|| {
+                self.morphology
+                    .iter()
+                    .find(|morphology| morphology.contains(key))
+                    .map(|morphology| morphology.count())
+            }),
+        }
+    }
+
+    fn longest_word(&self) -> usize {
+        self.layers
+            .iter()
+            .map(UserDictionary::longest_word)
+            .chain(
+                self.morphology
+                    .iter()
+                    .map(|morphology| morphology.longest_word()),
+            )
+            .fold(self.base.longest_word(), cmp::max)
     }
 
     fn should_check(&self, word: &str) -> bool {
@@ -44,7 +302,7 @@ impl Speller {
         if len == 1 && PUNCTUATION.contains(&word.chars().next().unwrap()) {
             return false;
         }
-        if len > self.word_frequency.longest_word + self.distance as usize {
+        if len > self.longest_word() + self.distance as usize {
             return false;
         }
         if word.to_lowercase() == "nan" {
@@ -57,41 +315,638 @@ impl Speller {
         true
     }
 
-    /// With same distance, return the word with the highest frequency. If multiple words have the same frequency, return first found (random).
+    /// With same distance, return the word with the highest frequency. If multiple words have the same frequency, return the first in alphabetical order.
     pub fn correction(&self, word: &str) -> Option<String> {
+        self.correction_with_score(word)
+            .map(|suggestion| suggestion.word)
+    }
+
+    /// Same as [`Speller::correction`], but also return the distance, count and score that
+    /// made the word win. A known word is returned as is with distance 0.
+    ///
+    /// With an [`ErrorModel`], the candidate with the highest score wins instead of the
+    /// closest one.
+    pub fn correction_with_score(&self, word: &str) -> Option<Suggestion> {
+        let mut suggestion = self.best_candidate(word)?;
+        if self.restore_case {
+            suggestion.word = self.restore_case(word, &suggestion.word);
+        }
+        Some(suggestion)
+    }
+
+    fn best_candidate(&self, word: &str) -> Option<Suggestion> {
         if !self.should_check(word) {
             return None;
         }
         if self.known(word) {
-            return Some(word.to_string());
+            let known = if self.restore_case {
+                self.normalize(word)
+            } else {
+                word.to_string()
+            };
+            return Some(self.suggestion(word, known, 0, self.count(word)));
         }
-        let word = if self.case_sensitive {
-            word.to_string()
+
+        let candidates = self.scored_candidates(word).into_iter();
+        if self.error_model.is_some() {
+            candidates.min_by(|a, b| {
+                b.score
+                    .total_cmp(&a.score)
+                    .then_with(|| a.word.cmp(&b.word))
+            })
         } else {
-            word.to_lowercase()
+            let word = self.normalize(word);
+            candidates
+                .map(|candidate| (self.edit_cost(&word, &candidate), candidate))
+                .min_by(|(cost_a, a), (cost_b, b)| {
+                    cost_a
+                        .total_cmp(cost_b)
+                        .then(b.count.cmp(&a.count))
+                        .then_with(|| a.word.cmp(&b.word))
+                })
+                .map(|(_, candidate)| candidate)
+        }
+    }
+
+    /// Return up to `max_results` candidates within the speller distance, best score first.
+    /// Unlike [`Speller::correction`], a frequent word at distance 2 may rank above a rare one
+    /// at distance 1. A known word is included with distance 0.
+    pub fn suggest(&self, word: &str, max_results: usize) -> Vec<Suggestion> {
+        if !self.should_check(word) {
+            return vec![];
+        }
+        let mut suggestions = self.scored_candidates(word);
+        suggestions.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.word.cmp(&b.word))
+        });
+        suggestions.truncate(max_results);
+        if self.restore_case {
+            for suggestion in suggestions.iter_mut() {
+                suggestion.word = self.restore_case(word, &suggestion.word);
+            }
+        }
+        suggestions
+    }
+
+    /// Give the dictionary form `correction` the case shape of `word`: Titlecase, UPPERCASE,
+    /// or camelCase when both have the same length. Words that only appear capitalized in the
+    /// dictionary keep their spelling, unless `word` is all uppercase.
+    pub fn restore_case(&self, word: &str, correction: &str) -> String {
+        if self.case_sensitive {
+            return correction.to_string();
+        }
+        let cased = self
+            .layers
+            .iter()
+            .rev()
+            .find_map(|layer| layer.cased(correction))
+            .or_else(|| self.base.cased(correction));
+        match (CasePattern::of(word), cased) {
+            (CasePattern::Upper, _) => correction.to_uppercase(),
+            (_, Some(cased)) => cased.to_string(),
+            (pattern, None) => pattern.apply_from(word, correction),
+        }
+    }
+
+    fn scored_candidates(&self, word: &str) -> Vec<Suggestion> {
+        let word = self.normalize(word);
+        self.lookup_words(&word, self.distance as u8)
+            .into_iter()
+            .map(|(candidate, distance, count)| self.suggestion(&word, candidate, distance, count))
+            .collect()
+    }
+
+    /// Edit distance from `typo` to `candidate`, with keyboard-aware substitutions when a
+    /// layout is configured.
+    fn edit_cost(&self, typo: &str, candidate: &Suggestion) -> f64 {
+        match &self.keyboard {
+            Some(keyboard) => keyboard_edit_distance(typo, &candidate.word, keyboard),
+            None => candidate.distance as f64,
+        }
+    }
+
+    /// Score `word` as a correction of `typo` with the noisy-channel model
+    /// P(word) × P(typo | word).
+    fn suggestion(&self, typo: &str, word: String, distance: u8, count: i32) -> Suggestion {
+        let probability = count.max(1) as f64 / self.base.total_count().max(1) as f64;
+        let channel = self.channel(typo, &word, distance);
+        Suggestion {
+            word,
+            distance,
+            count,
+            score: probability.ln() + channel,
+        }
+    }
+
+    /// ln P(typo | word), from the error model if there is one.
+    fn channel(&self, typo: &str, word: &str, distance: u8) -> f64 {
+        match &self.error_model {
+            Some(model) => model.log_probability(typo, word),
+            None => {
+                let cost = match &self.keyboard {
+                    Some(keyboard) => keyboard_edit_distance(typo, word, keyboard),
+                    None => distance as f64,
+                };
+                cost * EDIT_PROBABILITY.ln()
+            }
+        }
+    }
+
+    /// Correct `text` using the surrounding words to choose among candidates, which also fixes
+    /// real-word errors such as "a letter form him". Every word, known or not, is weighed
+    /// against its candidates with the n-gram counts of [`SpellerBuilder::ngram_file`].
+    /// Formatting is kept as in [`Speller::correct_text`].
+    ///
+    /// Context does not cross sentence punctuation (`.`, `!`, `?`, `;`, `:`), numbers, URLs or
+    /// emails. Hyphenated words are only used as context.
+    pub fn correct_sentence(&self, text: &str) -> String {
+        // Words with their byte span and the index of the sentence they belong to.
+        let mut words: Vec<(Range<usize>, &str, usize)> = vec![];
+        let (mut sentence, mut end) = (0, 0);
+        for token in tokenize(text) {
+            if token.kind != TokenKind::Word
+                || text[end..token.byte_span.start].contains(['.', '!', '?', ';', ':'])
+            {
+                sentence += 1;
+            }
+            end = token.byte_span.end;
+            if token.kind == TokenKind::Word {
+                words.push((token.byte_span, token.text, sentence));
+            }
+        }
+
+        let mut corrected = String::with_capacity(text.len());
+        let mut chosen: Vec<String> = vec![];
+        let mut end = 0;
+        for (i, (span, word, sentence)) in words.iter().enumerate() {
+            if i > 0 && words[i - 1].2 != *sentence {
+                chosen.clear();
+            }
+            let original = self.normalize(word);
+            let candidates = self.sentence_candidates(word, &original);
+            let left: Vec<&str> = chosen
+                .iter()
+                .rev()
+                .take(2)
+                .rev()
+                .map(String::as_str)
+                .collect();
+            let right: Vec<String> = words[i + 1..]
+                .iter()
+                .take_while(|(_, _, s)| s == sentence)
+                .take(2)
+                .map(|(_, word, _)| self.normalize(word))
+                .collect();
+
+            let best = candidates
+                .into_iter()
+                .map(|(candidate, channel)| {
+                    let score = channel + self.context_score(&left, &candidate, &right);
+                    (candidate, score)
+                })
+                .max_by(|a, b| a.1.total_cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+                .map(|(candidate, _)| candidate)
+                .unwrap_or(original.clone());
+
+            if best != original {
+                corrected.push_str(&text[end..span.start]);
+                corrected.push_str(&self.restore_case(word, &best));
+                end = span.end;
+            }
+            chosen.push(best);
+        }
+        corrected.push_str(&text[end..]);
+        corrected
+    }
+
+    /// Candidates for `word` in [`Speller::correct_sentence`] with their ln P(typo | word).
+    /// A known word keeps probability [`KEEP_PROBABILITY`] and the rest is shared evenly by its
+    /// neighbours; an unknown word is scored like in [`Speller::suggest`].
+    fn sentence_candidates(&self, word: &str, normalized: &str) -> Vec<(String, f64)> {
+        if word.contains('-') || !self.should_check(word) {
+            return vec![];
+        }
+        let mut candidates: Vec<(String, u8)> = self
+            .lookup_words(normalized, self.distance as u8)
+            .into_iter()
+            .filter(|&(_, distance, _)| distance > 0)
+            .map(|(candidate, distance, _)| (candidate, distance))
+            .collect();
+        if !self.known(word) {
+            return candidates
+                .into_iter()
+                .map(|(candidate, distance)| {
+                    let channel = self.channel(normalized, &candidate, distance);
+                    (candidate, channel)
+                })
+                .collect();
+        }
+        let share = ((1.0 - KEEP_PROBABILITY) / candidates.len().max(1) as f64).ln();
+        candidates.push((normalized.to_string(), 0));
+        candidates
+            .into_iter()
+            .map(|(candidate, distance)| {
+                let channel = if distance == 0 {
+                    KEEP_PROBABILITY.ln()
+                } else {
+                    share
+                };
+                (candidate, channel)
+            })
+            .collect()
+    }
+
+    /// ln of the language model probability of `word` between its `left` and `right` context.
+    fn context_score(&self, left: &[&str], word: &str, right: &[String]) -> f64 {
+        let total = self.base.total_count();
+        let unigram = |w: &str| self.count(w) as i64;
+        let mut context: Vec<&str> = left.to_vec();
+        let mut score = self.base.ngram_score(&context, word, unigram, total).ln();
+        context.push(word);
+        for next in right {
+            let start = context.len().saturating_sub(2);
+            score += self
+                .base
+                .ngram_score(&context[start..], next, unigram, total)
+                .ln();
+            context.push(next);
+        }
+        score
+    }
+
+    /// Tokenize `text` and return every misspelled word with its byte and char span, in order.
+    /// Numbers, URLs, emails and words rejected by `should_check` are skipped. An unknown
+    /// hyphenated word is checked part by part.
+    pub fn check_text(&self, text: &str) -> Vec<Misspelling> {
+        self.misspelled_words(text)
+            .into_iter()
+            .map(|(word, byte_span, char_span)| self.misspelling(word, byte_span, char_span))
+            .collect()
+    }
+
+    /// Check the identifiers, comments and string literals of the source code `text`,
+    /// written in `language`. The keywords of the language are skipped, and identifiers, in
+    /// the code or in prose, are split into their words, so `parseHttpRequest` is checked as
+    /// `parse`, `Http` and `Request`; parts shorter than 3 chars are skipped. Spans are
+    /// relative to the whole of `text`.
+    pub fn check_code(&self, text: &str, language: Language) -> Vec<Misspelling> {
+        self.misspelled_code_words(text, language)
+            .into_iter()
+            .map(|(word, byte_span, char_span)| self.misspelling(word, byte_span, char_span))
+            .collect()
+    }
+
+    /// The misspellings [`Speller::check_text`] finds, without looking up their suggestions,
+    /// which is most of the work. Their `suggestions` are empty.
+    pub fn find_misspellings(&self, text: &str) -> Vec<Misspelling> {
+        self.misspelled_words(text)
+            .into_iter()
+            .map(|(word, byte_span, char_span)| Misspelling::new(word, byte_span, char_span))
+            .collect()
+    }
+
+    /// The misspellings [`Speller::check_code`] finds, without their suggestions.
+    pub fn find_misspellings_in_code(&self, text: &str, language: Language) -> Vec<Misspelling> {
+        self.misspelled_code_words(text, language)
+            .into_iter()
+            .map(|(word, byte_span, char_span)| Misspelling::new(word, byte_span, char_span))
+            .collect()
+    }
+
+    /// Replace every misspelled word of `text` with its correction, keeping everything else
+    /// as is. Each correction takes the case shape of the word it replaces, as with
+    /// [`SpellerBuilder::restore_case`]. Words without a correction are left untouched.
+    pub fn correct_text(&self, text: &str) -> String {
+        let mut corrected = String::with_capacity(text.len());
+        let mut end = 0;
+        for (word, byte_span, _) in self.misspelled_words(text) {
+            if let Some(correction) = self.best_candidate(word) {
+                corrected.push_str(&text[end..byte_span.start]);
+                corrected.push_str(&self.restore_case(word, &correction.word));
+                end = byte_span.end;
+            }
+        }
+        corrected.push_str(&text[end..]);
+        corrected
+    }
+
+    /// The misspelled words of `text` with their byte and char spans.
+    fn misspelled_words<'a>(&self, text: &'a str) -> Vec<(&'a str, Range<usize>, Range<usize>)> {
+        let mut words = vec![];
+        for token in tokenize(text) {
+            if token.kind != TokenKind::Word {
+                continue;
+            }
+            if !token.text.contains('-') {
+                if self.is_misspelled(token.text) {
+                    words.push((token.text, token.byte_span, token.char_span));
+                }
+                continue;
+            }
+            if self.known(token.text) {
+                continue;
+            }
+            let (mut byte, mut char) = (token.byte_span.start, token.char_span.start);
+            for part in token.text.split('-') {
+                let length = part.chars().count();
+                if self.is_misspelled(part) {
+                    words.push((part, byte..byte + part.len(), char..char + length));
+                }
+                byte += part.len() + 1;
+                char += length + 1;
+            }
+        }
+        words
+    }
+
+    /// The misspelled words of the identifiers, comments and strings of the source code
+    /// `text`, with their byte and char spans in `text`.
+    fn misspelled_code_words<'a>(
+        &self,
+        text: &'a str,
+        language: Language,
+    ) -> Vec<(&'a str, Range<usize>, Range<usize>)> {
+        let mut words = vec![];
+        let (mut byte, mut char) = (0, 0);
+        for region in code::regions(text, language) {
+            let span = region.byte_span;
+            char += text[byte..span.start].chars().count();
+            let prose = code::prose(&text[span.clone()], region.kind);
+            for (_, byte_span, char_span) in self.misspelled_identifiers(&prose) {
+                // Only ASCII is blanked out of the prose, so the word is the same in `text`.
+                let byte_span = span.start + byte_span.start..span.start + byte_span.end;
+                let char_span = char + char_span.start..char + char_span.end;
+                words.push((&text[byte_span.clone()], byte_span, char_span));
+            }
+            char += text[span.clone()].chars().count();
+            byte = span.end;
+        }
+        words
+    }
+
+    /// The misspelled words of `text` with their byte and char spans, where a word that is
+    /// unknown as a whole is checked part by part with [`code::split_identifier`].
+    fn misspelled_identifiers<'a>(
+        &self,
+        text: &'a str,
+    ) -> Vec<(&'a str, Range<usize>, Range<usize>)> {
+        let mut words = vec![];
+        for token in tokenize(text) {
+            // The parts are checked on their own, however long the identifier is.
+            if token.kind != TokenKind::Word || self.known(token.text) {
+                continue;
+            }
+            let parts = code::split_identifier(token.text);
+            for part in &parts {
+                let word = &token.text[part.clone()];
+                let length = word.chars().count();
+                if parts.len() > 1 && length < 3 || !self.is_misspelled(word) {
+                    continue;
+                }
+                let byte = token.byte_span.start + part.start;
+                let char = token.char_span.start + token.text[..part.start].chars().count();
+                words.push((word, byte..byte + word.len(), char..char + length));
+            }
+        }
+        words
+    }
+
+    fn is_misspelled(&self, word: &str) -> bool {
+        !word.is_empty() && self.should_check(word) && !self.known(word)
+    }
+
+    fn misspelling(
+        &self,
+        word: &str,
+        byte_span: Range<usize>,
+        char_span: Range<usize>,
+    ) -> Misspelling {
+        Misspelling {
+            suggestions: self
+                .suggest(word, MAX_SUGGESTIONS)
+                .into_iter()
+                .map(|suggestion| suggestion.word)
+                .collect(),
+            ..Misspelling::new(word, byte_span, char_span)
+        }
+    }
+
+    /// Add `count` occurrences of `word` to the base dictionary, inserting it if it is
+    /// unknown. The count saturates at `i32::MAX`.
+    pub fn add_word(&mut self, word: &str, count: i32) {
+        let key = self.normalize(word);
+        if let Some(current) = self.base.count(&key) {
+            self.set_count(word, current.saturating_add(count));
+            return;
+        }
+        if key.is_empty() {
+            return;
+        }
+        let (word_frequency, index) = self.base.to_mut(self.case_sensitive);
+        let id = word_frequency.insert_word(word, count);
+        index.insert(&key, id);
+    }
+
+    /// Set the count of `word` in the base dictionary, inserting it if it is unknown.
+    pub fn set_count(&mut self, word: &str, count: i32) {
+        let key = self.normalize(word);
+        if self.base.count(&key).is_none() {
+            self.add_word(word, count);
+            return;
+        }
+        let (word_frequency, _) = self.base.to_mut(self.case_sensitive);
+        if let Some(current) = word_frequency.dictionary.get_mut(&key) {
+            word_frequency.total_count += count as i64 - *current as i64;
+            *current = count;
+        }
+    }
+
+    /// Remove `word` from the base dictionary. Return whether it was there.
+    pub fn remove_word(&mut self, word: &str) -> bool {
+        let key = self.normalize(word);
+        let Some(&(id, _)) = self.lookup(&key, 0).first() else {
+            return false;
         };
+        let (word_frequency, index) = self.base.to_mut(self.case_sensitive);
+        index.remove(&key, id);
+        // The last word of the list takes the place of the removed one.
+        let last = word_frequency.list.len() as u32 - 1;
+        if id != last {
+            index.relabel(&word_frequency.list[last as usize], last, id);
+        }
+        word_frequency.remove_word(&key, id);
+        true
+    }
 
-        let dfa = self.automaton_builder.build_dfa(&word);
+    /// Return the id in the base dictionary and the distance of every word within
+    /// `distance` of `word`, in no particular order.
+    fn lookup(&self, word: &str, distance: u8) -> Vec<(u32, u8)> {
+        self.base.lookup(&self.automaton_builder, word, distance)
+    }
 
-        let mut best_match: Option<(String, u8)> = None;
+    /// Return every word within `distance` of the normalized `word` as seen through the
+    /// layers, with its distance and count. Base words come first, by id, then the words
+    /// added by the layers, in alphabetical order. Banned words are left out.
+    fn lookup_words(&self, word: &str, distance: u8) -> Vec<(String, u8, i32)> {
+        let mut base = self.lookup(word, distance);
+        base.sort_unstable();
+        let mut matches: Vec<(String, u8, i32)> = base
+            .into_iter()
+            .filter_map(|(id, distance)| {
+                let candidate = self.base.word(id);
+                let count = self.count_of(candidate)?;
+                Some((candidate.to_string(), distance, count))
+            })
+            .collect();
 
-        for item in self.word_frequency.list.iter() {
-            match dfa.eval(item) {
-                Distance::Exact(distance) if distance <= self.distance as u8 => {
-                    // match exact word
-                    if distance <= 1 {
-                        return Some(item.to_string());
+        let mut added: Vec<(String, u8, i32)> = vec![];
+        let mut seen: HashSet<String> = HashSet::new();
+        if !self.layers.is_empty() {
+            let dfa = self.automaton_builder.build_dfa(word);
+            for layer in self.layers.iter() {
+                for (candidate, distance) in layer.search(&dfa, distance) {
+                    if self.base.count(candidate).is_some() || seen.contains(candidate) {
+                        continue;
                     }
-                    // add to best match
-                    if best_match.as_ref().is_none_or(|&(_, d)| distance < d) {
-                        best_match = Some((item.to_string(), distance));
+                    if let Some(count) = self.count_of(candidate) {
+                        seen.insert(candidate.to_string());
+                        added.push((candidate.to_string(), distance, count));
                     }
                 }
-                _ => {}
             }
         }
+        for morphology in self.morphology.iter() {
+            for (candidate, distance) in
+                morphology.candidates(&self.automaton_builder, word, distance)
+            {
+                if self.base.count(&candidate).is_some() || seen.contains(&candidate) {
+                    continue;
+                }
+                if let Some(count) = self.count_of(&candidate) {
+                    seen.insert(candidate.clone());
+                    added.push((candidate, distance, count));
+                }
+            }
+        }
+        added.sort_unstable();
+        matches.append(&mut added);
+        matches
+    }
+
+    /// Put `layer` on top of the stack of user dictionaries. It takes precedence over the
+    /// base dictionary and the layers pushed before it. Its words are normalized to the case
+    /// sensitivity of the speller.
+    pub fn push_layer(&mut self, mut layer: UserDictionary) {
+        layer.set_case_sensitive(self.case_sensitive);
+        self.layers.push(layer);
+    }
+
+    /// Remove the top layer and return it.
+    pub fn pop_layer(&mut self) -> Option<UserDictionary> {
+        self.layers.pop()
+    }
+
+    /// The user dictionaries, from the bottom of the stack to the top.
+    pub fn layers(&self) -> &[UserDictionary] {
+        &self.layers
+    }
+
+    pub fn layer_mut(&mut self, index: usize) -> Option<&mut UserDictionary> {
+        self.layers.get_mut(index)
+    }
+
+    /// Write a binary image of the speller: its settings, dictionary, index, n-grams, error
+    /// model and keyboard layout. Loading it with [`Speller::load`] skips parsing and
+    /// sorting the dictionary files. User dictionary layers are not included.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let (word_frequency, index) = match &self.base {
+            Base::Owned(word_frequency, index) => (word_frequency, index),
+            // The image of a mapped speller is the mapped file itself.
+            Base::Mapped(mapped) => {
+                writer.write_all(mapped.bytes())?;
+                return writer.flush();
+            }
+        };
+        let mut image = ImageWriter::new();
+        let backend = match index.as_ref() {
+            Index::Automaton(_) => 0,
+            Index::SymmetricDelete(_) => 1,
+        };
+        image.array([
+            self.distance as u32,
+            self.case_sensitive as u32,
+            self.restore_case as u32,
+            backend,
+        ]);
+        word_frequency.write_image(&mut image);
+        match index.as_ref() {
+            Index::Automaton(trie) => trie.write_image(&mut image),
+            Index::SymmetricDelete(index) => index.write_image(&mut image),
+        }
+        image.flag(self.error_model.is_some());
+        if let Some(model) = &self.error_model {
+            model.write_image(&mut image);
+        }
+        image.flag(self.keyboard.is_some());
+        if let Some(keyboard) = &self.keyboard {
+            keyboard.write_image(&mut image);
+        }
+        image.finish(writer)
+    }
 
-        best_match.map(|(word, _)| word)
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+
+    /// Read a speller from an image written by [`Speller::write_to`]. The header, format
+    /// version and checksum are verified first.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Speller, BuildError> {
+        let view = ImageView::read(bytes)?;
+        let word_frequency =
+            WordFrequency::from_view(view.dictionary, view.settings.case_sensitive);
+        if word_frequency.dictionary.len() != word_frequency.list.len() {
+            return Err(BuildError::InvalidImage);
+        }
+        let base = Base::Owned(Arc::new(word_frequency), Arc::new(view.index.to_index()));
+        Ok(Speller::from_settings(view.settings, base))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Speller, BuildError> {
+        Speller::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Open an image written by [`Speller::save`] as a memory map and use it in place,
+    /// without copying the dictionary or index into memory. Every process that maps the same
+    /// file shares its pages, so extra workers cost next to no memory.
+    ///
+    /// The file must not be modified or truncated while a speller uses it. Words can still
+    /// be added with user dictionary layers; changing the base dictionary with
+    /// [`Speller::add_word`] and friends copies it into memory first.
+    pub fn load_mapped<P: AsRef<Path>>(path: P) -> Result<Speller, BuildError> {
+        let (mapped, settings) = MappedDictionary::open(path)?;
+        Ok(Speller::from_settings(
+            settings,
+            Base::Mapped(Arc::new(mapped)),
+        ))
+    }
+
+    fn from_settings(settings: Settings, base: Base) -> Speller {
+        Speller {
+            distance: settings.distance as i32,
+            case_sensitive: settings.case_sensitive,
+            automaton_builder: Arc::new(LevenshteinAutomatonBuilder::new(
+                settings.distance as u8,
+                true,
+            )),
+            base,
+            layers: vec![],
+            morphology: vec![],
+            error_model: settings.error_model,
+            keyboard: settings.keyboard,
+            restore_case: settings.restore_case,
+        }
     }
 
     /// Return all possible candidates with the given distance.
@@ -105,8 +960,6 @@ impl Speller {
             word.to_lowercase()
         };
 
-        let dfa = self.automaton_builder.build_dfa(&word);
-
         let mut matches: Vec<Vec<String>> = vec![Vec::new(); (distance as usize) + 1];
 
         if self.known(&word) {
@@ -114,19 +967,12 @@ impl Speller {
             return Some(matches);
         }
 
-        let mut found = false;
-
-        for item in self.word_frequency.list.iter() {
-            match dfa.eval(item) {
-                Distance::Exact(dist) if dist as usize <= distance as usize => {
-                    matches[dist as usize].push(item.to_string());
-                    found = true;
-                }
-                _ => {}
-            }
+        let results = self.lookup_words(&word, distance);
+        for (candidate, dist, _) in results.iter() {
+            matches[*dist as usize].push(candidate.to_string());
         }
 
-        if found {
+        if !results.is_empty() {
             Some(matches)
         } else {
             None
@@ -225,19 +1071,82 @@ pub fn edit_distance(a: &str, b: &str, limit: usize) -> Option<usize> {
     (distance <= limit).then_some(distance)
 }
 
+/// Same as [`edit_distance`], but substituting a key with one of its neighbours on `keyboard`
+/// costs [`keyboard::ADJACENT_SUBSTITUTION_COST`] instead of 1.
+pub fn keyboard_edit_distance(a: &str, b: &str, keyboard: &Keyboard) -> f64 {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+
+    let mut prev_prev = vec![f64::INFINITY; b.len() + 1];
+    let mut prev = (0..=b.len()).map(|j| j as f64).collect::<Vec<_>>();
+    let mut current = vec![0.0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i as f64;
+        for j in 1..=b.len() {
+            current[j] = (prev[j] + 1.0)
+                .min(current[j - 1] + 1.0)
+                .min(prev[j - 1] + keyboard.substitution_cost(a[i - 1], b[j - 1]));
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                // transposition
+                current[j] = current[j].min(prev_prev[j - 2] + 1.0);
+            }
+        }
+        [prev_prev, prev, current] = [prev, current, prev_prev];
+    }
+
+    prev[b.len()]
+}
+
 pub struct SpellerBuilder {
     dict_file: Vec<String>,
     distance: i32,
     case_sensitive: bool,
     dict_source: Vec<HashMap<String, i32>>,
+    backend: Backend,
+    error_model: Option<ErrorModel>,
+    keyboard: Option<Keyboard>,
+    restore_case: bool,
+    ngram_file: Vec<String>,
+    morphology: bool,
+    cutoff: Cutoff,
+    word_list_count: i32,
+    dict_input: Vec<(DictInput, Format)>,
+    loaders: HashMap<String, LoaderFactory>,
+}
+
+/// A dictionary given to the builder as bytes or a reader instead of a file.
+enum DictInput {
+    Bytes(Cow<'static, [u8]>),
+    /// Taken by the first build.
+    Reader(Mutex<Option<Box<dyn io::Read + Send>>>),
 }
 
+/// Loads a dictionary with a new instance of a loader registered for an extension.
+type LoaderFactory =
+    Arc<dyn Fn(&mut dyn io::Read) -> Result<HashMap<String, i32>, BuildError> + Send + Sync>;
+
 impl Default for SpellerBuilder {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// The `.aff` file next to a `.dic` file. A compressed `.dic` file may come with an `.aff`
+/// file compressed the same way.
+fn affix_path(dic: &Path) -> PathBuf {
+    let aff = compression::strip_extension(dic).with_extension("aff");
+    match dic.extension() {
+        Some(extension) if !aff.exists() && Compression::from_path(dic).is_some() => {
+            let mut compressed = aff.into_os_string();
+            compressed.push(".");
+            compressed.push(extension);
+            compressed.into()
+        }
+        _ => aff,
+    }
+}
+
 impl SpellerBuilder {
     pub fn new() -> SpellerBuilder {
         SpellerBuilder {
@@ -245,6 +1154,16 @@ impl SpellerBuilder {
             distance: 2,
             case_sensitive: false,
             dict_source: vec![],
+            backend: Backend::default(),
+            error_model: None,
+            keyboard: None,
+            restore_case: false,
+            ngram_file: vec![],
+            morphology: false,
+            cutoff: Cutoff::default(),
+            word_list_count: 1,
+            dict_input: vec![],
+            loaders: HashMap::new(),
         }
     }
 
@@ -253,6 +1172,45 @@ impl SpellerBuilder {
         self
     }
 
+    /// Load a dictionary in `format` from `bytes`, such as a file embedded with
+    /// `include_bytes!`. Compressed bytes are decompressed as files are.
+    pub fn dict_bytes<B: Into<Cow<'static, [u8]>>>(
+        &mut self,
+        bytes: B,
+        format: Format,
+    ) -> &mut Self {
+        self.dict_input
+            .push((DictInput::Bytes(bytes.into()), format));
+        self
+    }
+
+    /// Load a dictionary in `format` from `reader`, such as a download from object storage.
+    /// The reader is read by the first call to [`SpellerBuilder::build`]; building again fails
+    /// with [`BuildError::ReaderConsumed`].
+    pub fn dict_reader<R: io::Read + Send + 'static>(
+        &mut self,
+        reader: R,
+        format: Format,
+    ) -> &mut Self {
+        let reader: Box<dyn io::Read + Send> = Box::new(reader);
+        self.dict_input
+            .push((DictInput::Reader(Mutex::new(Some(reader))), format));
+        self
+    }
+
+    /// Load the dictionary files with `extension`, and input in
+    /// [`Format::Extension`] with that extension, with a new loader made by `loader` for each
+    /// of them. Registered loaders take precedence over the built-in formats.
+    pub fn register_loader<W, F>(&mut self, extension: &str, loader: F) -> &mut Self
+    where
+        W: WordLoader,
+        F: Fn() -> W + Send + Sync + 'static,
+    {
+        let factory: LoaderFactory = Arc::new(move |reader| loader().load_words(reader));
+        self.loaders.insert(extension.to_string(), factory);
+        self
+    }
+
     pub fn distance(&mut self, distance: i32) -> &mut Self {
         self.distance = distance;
         self
@@ -268,58 +1226,218 @@ impl SpellerBuilder {
         self
     }
 
+    pub fn backend(&mut self, backend: Backend) -> &mut Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Rank candidates with a noisy-channel error model instead of raw edit distance.
+    pub fn error_model(&mut self, error_model: ErrorModel) -> &mut Self {
+        self.error_model = Some(error_model);
+        self
+    }
+
+    /// Make substitutions between neighbouring keys cheaper when ranking candidates. The error
+    /// model, if any, uses this layout too unless it has its own.
+    pub fn keyboard(&mut self, keyboard: Keyboard) -> &mut Self {
+        self.keyboard = Some(keyboard);
+        self
+    }
+
+    /// When the speller is case-insensitive, return corrections in the case shape of the
+    /// input ("Helo" gives "Hello", "HELO" gives "HELLO") instead of the lowercase dictionary
+    /// form. Words that only appear capitalized in the dictionary, such as proper nouns, keep
+    /// their spelling.
+    pub fn restore_case(&mut self, restore_case: bool) -> &mut Self {
+        self.restore_case = restore_case;
+        self
+    }
+
+    /// Bigram and trigram count files for [`Speller::correct_sentence`], see
+    /// [`WordFrequency::load_ngrams`] for the format.
+    pub fn ngram_file(&mut self, ngram_file: Vec<String>) -> &mut Self {
+        self.ngram_file = ngram_file;
+        self
+    }
+
+    /// Keep the stems and affix rules of Hunspell `.dic` files instead of expanding them into
+    /// every form. Inflected words are recognized and suggested from their stems, which keeps
+    /// the dictionary small for heavily inflected languages. The morphology is not included in
+    /// images written by [`Speller::save`].
+    pub fn morphology(&mut self, morphology: bool) -> &mut Self {
+        self.morphology = morphology;
+        self
+    }
+
+    /// The count given to every word of plain word lists, which have no counts. 1 by default.
+    pub fn word_list_count(&mut self, count: i32) -> &mut Self {
+        self.word_list_count = count;
+        self
+    }
+
+    /// Drop the words of each frequency list with a count below `min_count` while it is read.
+    pub fn min_count(&mut self, min_count: i32) -> &mut Self {
+        self.cutoff.min_count = Some(min_count);
+        self
+    }
+
+    /// Keep only the `top_n` most frequent words of each frequency list. At most `top_n`
+    /// words are held in memory while a list is read.
+    pub fn top_n(&mut self, top_n: usize) -> &mut Self {
+        self.cutoff.top_n = Some(top_n);
+        self
+    }
+
+    /// Load a dictionary in `format` from `reader` into `word_frequency`.
+    fn load<R: io::Read>(
+        &self,
+        word_frequency: &mut WordFrequency,
+        mut reader: R,
+        format: &Format,
+    ) -> Result<(), BuildError> {
+        match format {
+            Format::Extension(extension) => {
+                let loader = self
+                    .loaders
+                    .get(extension)
+                    .ok_or(BuildError::FileTypeNotSupported)?;
+                word_frequency.load_dict(loader(&mut reader)?)
+            }
+            #[cfg(feature = "serde_json")]
+            Format::Json => {
+                let mut loader = JsonWordLoader::default();
+                word_frequency.load_stream(&mut loader, reader, self.cutoff)
+            }
+            #[cfg(feature = "csv")]
+            Format::Csv => {
+                let mut loader = CsvWordLoader::default();
+                word_frequency.load_stream(&mut loader, reader, self.cutoff)
+            }
+            #[cfg(feature = "csv")]
+            Format::Tsv => {
+                let mut loader = CsvWordLoader::new().with_delimiter(b'\t');
+                word_frequency.load_stream(&mut loader, reader, self.cutoff)
+            }
+            Format::Text => {
+                let mut loader = TextWordLoader::default();
+                word_frequency.load_stream(&mut loader, reader, self.cutoff)
+            }
+            Format::WordList => {
+                let mut loader = TextWordLoader::word_list()
+                    .with_default_count(self.word_list_count)
+                    .with_dedup(true);
+                word_frequency.load_stream(&mut loader, reader, self.cutoff)
+            }
+        }
+    }
+
     pub fn build(&self) -> Result<Speller, BuildError> {
-        let mut speller = Speller {
-            distance: self.distance,
-            case_sensitive: self.case_sensitive,
-            automaton_builder: LevenshteinAutomatonBuilder::new(self.distance as u8, true),
-            word_frequency: WordFrequency::new(self.case_sensitive),
-        };
+        let mut word_frequency = WordFrequency::new(self.case_sensitive);
+        let mut morphology = vec![];
 
         for local_dictionary in self.dict_file.iter() {
             let path = Path::new(local_dictionary);
-            match path.extension().and_then(OsStr::to_str) {
-                #[cfg(feature = "serde_json")]
-                Some("json") => {
-                    let mut loader = JsonWordLoader::default();
-                    speller.word_frequency.load_file(&mut loader, path)?;
+            // `en.json.gz` is read as JSON.
+            let extension = compression::strip_extension(path)
+                .extension()
+                .and_then(OsStr::to_str)
+                .unwrap_or_default()
+                .to_string();
+            match extension.as_str() {
+                extension if self.loaders.contains_key(extension) => {
+                    let format = Format::Extension(extension.to_string());
+                    self.load(&mut word_frequency, compression::open(path)?, &format)?;
                 }
-                #[cfg(feature = "csv")]
-                Some("csv") => {
-                    let mut loader = CsvWordLoader::default();
-                    speller.word_frequency.load_file(&mut loader, path)?;
+                // The affix rules are read from the `.aff` file next to the `.dic` file.
+                "dic" if self.morphology => {
+                    let affixes = Affixes::from_file(affix_path(path))?;
+                    let stems = Morphology::from_file(affixes, path, self.case_sensitive)?;
+                    word_frequency.load_dict(stems.words())?;
+                    morphology.push(Arc::new(stems));
                 }
-                #[cfg(feature = "csv")]
-                Some("tsv") => {
-                    let mut loader = CsvWordLoader::new().with_delimiter(b'\t');
-                    speller.word_frequency.load_file(&mut loader, path)?;
+                "dic" => {
+                    let mut loader = HunspellWordLoader::from_aff_file(affix_path(path))?;
+                    word_frequency.load_file(&mut loader, path)?;
                 }
-                Some("txt") => {
-                    let mut loader = TextWordLoader::default();
-                    speller.word_frequency.load_file(&mut loader, path)?;
+                extension => {
+                    let format = Format::from_extension(extension);
+                    self.load(&mut word_frequency, compression::open(path)?, &format)?;
                 }
-                _ => return Err(BuildError::FileTypeNotSupported),
             };
         }
 
+        for (input, format) in self.dict_input.iter() {
+            match input {
+                DictInput::Bytes(bytes) => {
+                    let reader = compression::decompress(&bytes[..])?;
+                    self.load(&mut word_frequency, reader, format)?;
+                }
+                DictInput::Reader(reader) => {
+                    let reader = reader
+                        .lock()
+                        .unwrap_or_else(|error| error.into_inner())
+                        .take()
+                        .ok_or(BuildError::ReaderConsumed)?;
+                    let reader = compression::decompress(BufReader::new(reader))?;
+                    self.load(&mut word_frequency, reader, format)?;
+                }
+            }
+        }
+
         for dict in self.dict_source.iter() {
-            speller.word_frequency.load_dict(dict.clone())?;
+            word_frequency.load_dict(dict.clone())?;
+        }
+
+        for ngram_file in self.ngram_file.iter() {
+            word_frequency.load_ngram_file(ngram_file)?;
         }
 
-        if speller.word_frequency.unique_words == 0 {
+        if word_frequency.unique_words == 0 {
             return Err(BuildError::DictNotFound);
         }
 
-        Ok(speller)
+        let list = &word_frequency.list;
+        let index = match self.backend {
+            Backend::Automaton => Index::Automaton(Trie::from_words(list)),
+            Backend::SymmetricDelete => {
+                Index::SymmetricDelete(DeleteIndex::from_words(list, self.distance as usize))
+            }
+        };
+
+        Ok(Speller {
+            distance: self.distance,
+            case_sensitive: self.case_sensitive,
+            automaton_builder: Arc::new(LevenshteinAutomatonBuilder::new(
+                self.distance as u8,
+                true,
+            )),
+            base: Base::Owned(Arc::new(word_frequency), Arc::new(index)),
+            layers: vec![],
+            morphology,
+            error_model: self.error_model.clone().map(|model| {
+                match (&self.keyboard, model.keyboard()) {
+                    (Some(keyboard), None) => model.with_keyboard(keyboard.clone()),
+                    _ => model,
+                }
+            }),
+            keyboard: self.keyboard.clone(),
+            restore_case: self.restore_case,
+        })
     }
 }
 
+#[derive(Clone)]
 pub struct WordFrequency {
     dictionary: HashMap<String, i32>,
     list: Vec<String>,
     unique_words: i32,
+    total_count: i64,
     case_sensitive: bool,
     longest_word: usize,
+    /// Original spelling of the words that only appear capitalized in case-insensitive
+    /// dictionaries, such as proper nouns, keyed by their lowercase form.
+    cased: HashMap<String, String>,
+    ngrams: NgramModel,
 }
 
 impl WordFrequency {
@@ -328,8 +1446,11 @@ impl WordFrequency {
             dictionary: HashMap::new(),
             list: vec![],
             unique_words: 0,
+            total_count: 0,
             case_sensitive,
             longest_word: 0,
+            cased: HashMap::new(),
+            ngrams: NgramModel::default(),
         }
     }
 
@@ -338,17 +1459,103 @@ impl WordFrequency {
         W: WordLoader,
         P: AsRef<Path>,
     {
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
-        let data = source.load_words(reader)?;
+        let data = source.load_words(compression::open(path)?)?;
         self.load_dict(data)?;
         Ok(())
     }
 
+    /// Load words one at a time as `loader` reads them from `reader`, without collecting the
+    /// whole file first. Words below `cutoff.min_count` are dropped as they are read; with
+    /// `cutoff.top_n`, the most frequent words are kept in a heap of that size, ties going to
+    /// the word that sorts first. As with [`WordFrequency::load_file`], the last count of a
+    /// word listed several times wins. Only the words kept so far are remembered, so a word
+    /// that falls out of the top words when listed again with a lower count doesn't bring
+    /// back a word dropped before it.
+    pub fn load_stream<W, R>(
+        &mut self,
+        loader: &mut W,
+        reader: R,
+        cutoff: Cutoff,
+    ) -> Result<(), BuildError>
+    where
+        W: StreamingWordLoader,
+        R: io::Read,
+    {
+        let min_count = cutoff.min_count.unwrap_or(i32::MIN);
+        let mut kept = HashMap::new();
+        match cutoff.top_n {
+            Some(top_n) => {
+                let mut heap = BinaryHeap::with_capacity(top_n + 1);
+                loader.stream_words(reader, |word, count| {
+                    // A word seen again leaves the heap with its old count.
+                    if kept.remove(&word).is_some() {
+                        heap.retain(|Reverse((_, Reverse(held)))| *held != word);
+                    }
+                    if count >= min_count {
+                        kept.insert(word.clone(), count);
+                        heap.push(Reverse((count, Reverse(word))));
+                        if heap.len() > top_n {
+                            if let Some(Reverse((_, Reverse(dropped)))) = heap.pop() {
+                                kept.remove(&dropped);
+                            }
+                        }
+                    }
+                })?;
+            }
+            None => loader.stream_words(reader, |word, count| {
+                if count >= min_count {
+                    kept.insert(word, count);
+                } else {
+                    kept.remove(&word);
+                }
+            })?,
+        }
+        self.load_dict(kept)
+    }
+
+    pub fn stream_file<W, P>(
+        &mut self,
+        loader: &mut W,
+        path: P,
+        cutoff: Cutoff,
+    ) -> Result<(), BuildError>
+    where
+        W: StreamingWordLoader,
+        P: AsRef<Path>,
+    {
+        self.load_stream(loader, compression::open(path)?, cutoff)
+    }
+
+    /// Load bigram and trigram counts: tab-separated lines of two or three words followed by
+    /// their count, such as `letter\tfrom\t120`.
+    pub fn load_ngrams<R: io::Read>(&mut self, reader: R) -> Result<(), BuildError> {
+        self.ngrams.load(reader, self.case_sensitive)
+    }
+
+    pub fn load_ngram_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), BuildError> {
+        self.load_ngrams(compression::open(path)?)
+    }
+
     pub fn load_dict(&mut self, dict: HashMap<String, i32>) -> Result<(), BuildError> {
-        dict.iter().for_each(|(word, count)| {
-            self.add_word(word.to_string(), *count);
-        });
+        if !self.case_sensitive {
+            let mut cased: Vec<(&String, &i32)> = dict
+                .iter()
+                .filter(|(word, _)| {
+                    let lowercase = word.to_lowercase();
+                    **word != lowercase && !dict.contains_key(&lowercase)
+                })
+                .collect();
+            // The most frequent spelling wins when a word comes in several cases.
+            cased.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+            for (word, _) in cased {
+                self.cased
+                    .entry(word.to_lowercase())
+                    .or_insert_with(|| word.to_string());
+            }
+        }
+        for (word, count) in dict {
+            self.add_word(word, count);
+        }
         self.update();
         Ok(())
     }
@@ -359,15 +1566,89 @@ impl WordFrequency {
         } else {
             word.to_lowercase()
         };
-        *self.dictionary.entry(word).or_insert(0) += count;
+        let entry = self.dictionary.entry(word).or_insert(0);
+        *entry = entry.saturating_add(count);
     }
+    /// Insert a word that is not in the dictionary yet and return its id in `list`.
+    fn insert_word(&mut self, word: &str, count: i32) -> u32 {
+        let key = if self.case_sensitive {
+            word.to_string()
+        } else {
+            word.to_lowercase()
+        };
+        if key != word {
+            self.cased.insert(key.clone(), word.to_string());
+        }
+        self.longest_word = self.longest_word.max(key.len());
+        self.dictionary.insert(key.clone(), count);
+        self.list.push(key);
+        self.unique_words += 1;
+        self.total_count += count as i64;
+        self.list.len() as u32 - 1
+    }
+
+    /// Remove `word`, whose id in `list` is `id`. The last word of `list` takes its place.
+    fn remove_word(&mut self, word: &str, id: u32) {
+        if let Some(count) = self.dictionary.remove(word) {
+            self.total_count -= count as i64;
+        }
+        self.cased.remove(word);
+        self.list.swap_remove(id as usize);
+        self.unique_words -= 1;
+        if word.len() == self.longest_word {
+            self.longest_word = self.list.iter().map(|word| word.len()).max().unwrap_or(0);
+        }
+    }
+
+    /// Write the words in id order with their counts, the cased spellings sorted by key and
+    /// the n-grams.
+    fn write_image(&self, image: &mut ImageWriter) {
+        image.strings(self.list.iter());
+        image.array(self.list.iter().map(|word| self.dictionary[word]));
+        let mut cased: Vec<(&String, &String)> = self.cased.iter().collect();
+        cased.sort();
+        image.strings(cased.iter().map(|(key, _)| key));
+        image.strings(cased.iter().map(|(_, word)| word));
+        self.ngrams.write_image(image);
+    }
+
+    /// Copy the dictionary read from an image into memory. Duplicate words are not checked.
+    fn from_view(view: DictionaryView, case_sensitive: bool) -> WordFrequency {
+        let mut word_frequency = WordFrequency::new(case_sensitive);
+        word_frequency.list = view.words.iter().map(str::to_string).collect();
+        word_frequency.dictionary = word_frequency
+            .list
+            .iter()
+            .cloned()
+            .zip(view.counts.iter())
+            .collect();
+        word_frequency.cased = view
+            .cased_keys
+            .iter()
+            .map(str::to_string)
+            .zip(view.cased_words.iter().map(str::to_string))
+            .collect();
+        word_frequency.ngrams = view.ngrams.to_model();
+        word_frequency.unique_words = word_frequency.list.len() as i32;
+        word_frequency.total_count = view.counts.iter().map(i64::from).sum();
+        word_frequency.longest_word = word_frequency
+            .list
+            .iter()
+            .map(|word| word.len())
+            .max()
+            .unwrap_or(0);
+        word_frequency
+    }
+
     fn update(&mut self) {
         let mut map_vec: Vec<(&String, &i32)> = self.dictionary.iter().collect();
-        map_vec.sort_by_key(|a| a.1);
+        map_vec.sort_by(|a, b| a.1.cmp(b.1).then_with(|| a.0.cmp(b.0)));
+        self.list.clear();
         map_vec.iter().for_each(|(word, _count)| {
             self.list.push(word.to_string());
         });
         self.unique_words = self.dictionary.len() as i32;
+        self.total_count = self.dictionary.values().map(|&count| count as i64).sum();
         self.longest_word = self
             .dictionary
             .keys()
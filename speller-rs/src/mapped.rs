@@ -0,0 +1,242 @@
+use crate::error::BuildError;
+use crate::error_model::ErrorModel;
+use crate::image::{Array, ImageReader, Strings};
+use crate::keyboard::Keyboard;
+use crate::ngram::NgramView;
+use crate::symspell::DeleteIndexView;
+use crate::trie::TrieView;
+use crate::{Index, WordFrequency};
+use levenshtein_automata::LevenshteinAutomatonBuilder;
+use memmap2::Mmap;
+use std::fs::File;
+use std::path::Path;
+
+/// An image written by [`Speller::write_to`](crate::Speller::write_to), with views on the
+/// sections of its dictionary and index.
+pub(crate) struct ImageView<'a> {
+    pub settings: Settings,
+    pub dictionary: DictionaryView<'a>,
+    pub index: IndexView<'a>,
+}
+
+/// The speller settings stored in an image.
+pub(crate) struct Settings {
+    pub distance: u32,
+    pub case_sensitive: bool,
+    pub restore_case: bool,
+    pub error_model: Option<ErrorModel>,
+    pub keyboard: Option<Keyboard>,
+}
+
+impl<'a> ImageView<'a> {
+    /// Check the header and checksum of `bytes`, then read the sections of the image.
+    pub fn read(bytes: &'a [u8]) -> Result<ImageView<'a>, BuildError> {
+        let mut image = ImageReader::new(bytes)?;
+        let settings = image.array::<u32>()?;
+        if settings.len() != 4 {
+            return Err(BuildError::InvalidImage);
+        }
+        let dictionary = DictionaryView::read(&mut image)?;
+        let words = dictionary.words.len();
+        let index = match settings.get(3) {
+            0 => IndexView::Automaton(TrieView::read(&mut image, words)?),
+            1 => IndexView::SymmetricDelete(DeleteIndexView::read(&mut image, words)?),
+            _ => return Err(BuildError::InvalidImage),
+        };
+        let error_model = match image.flag()? {
+            true => Some(ErrorModel::from_image(&mut image)?),
+            false => None,
+        };
+        let keyboard = match image.flag()? {
+            true => Some(Keyboard::from_image(&mut image)?),
+            false => None,
+        };
+        Ok(ImageView {
+            settings: Settings {
+                distance: settings.get(0),
+                case_sensitive: settings.get(1) != 0,
+                restore_case: settings.get(2) != 0,
+                error_model,
+                keyboard,
+            },
+            dictionary,
+            index,
+        })
+    }
+}
+
+/// The sections written by `WordFrequency::write_image`.
+#[derive(Clone, Copy)]
+pub(crate) struct DictionaryView<'a> {
+    pub words: Strings<'a>,
+    pub counts: Array<'a, i32>,
+    pub cased_keys: Strings<'a>,
+    pub cased_words: Strings<'a>,
+    pub ngrams: NgramView<'a>,
+}
+
+impl<'a> DictionaryView<'a> {
+    fn read(image: &mut ImageReader<'a>) -> Result<DictionaryView<'a>, BuildError> {
+        let view = DictionaryView {
+            words: image.strings()?,
+            counts: image.array()?,
+            cased_keys: image.strings()?,
+            cased_words: image.strings()?,
+            ngrams: NgramView::read(image)?,
+        };
+        if view.words.len() != view.counts.len() || view.cased_keys.len() != view.cased_words.len()
+        {
+            return Err(BuildError::InvalidImage);
+        }
+        Ok(view)
+    }
+
+    fn rebind<'b>(&self, image: &'b [u8]) -> DictionaryView<'b> {
+        DictionaryView {
+            words: self.words.rebind(image),
+            counts: self.counts.rebind(image),
+            cased_keys: self.cased_keys.rebind(image),
+            cased_words: self.cased_words.rebind(image),
+            ngrams: self.ngrams.rebind(image),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum IndexView<'a> {
+    Automaton(TrieView<'a>),
+    SymmetricDelete(DeleteIndexView<'a>),
+}
+
+impl<'a> IndexView<'a> {
+    pub fn to_index(self) -> Index {
+        match self {
+            IndexView::Automaton(trie) => Index::Automaton(trie.to_trie()),
+            IndexView::SymmetricDelete(index) => Index::SymmetricDelete(index.to_index()),
+        }
+    }
+
+    fn rebind<'b>(&self, image: &'b [u8]) -> IndexView<'b> {
+        match self {
+            IndexView::Automaton(trie) => IndexView::Automaton(trie.rebind(image)),
+            IndexView::SymmetricDelete(index) => IndexView::SymmetricDelete(index.rebind(image)),
+        }
+    }
+}
+
+/// A read-only dictionary and index used in place from a memory-mapped image. Every process
+/// mapping the same file shares its pages.
+pub(crate) struct MappedDictionary {
+    map: Mmap,
+    // Views on the sections of `map`, kept detached and rebound to it on each use.
+    dictionary: DictionaryView<'static>,
+    index: IndexView<'static>,
+    total_count: i64,
+    longest_word: usize,
+}
+
+impl MappedDictionary {
+    /// Map the image at `path` and read its sections. The file must not be modified while
+    /// it is mapped.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<(MappedDictionary, Settings), BuildError> {
+        let file = File::open(path)?;
+        // SAFETY: the map is read-only. Like any file mapping, it is only sound as long as the
+        // file is not truncated or modified, as documented on `Speller::load_mapped`.
+        let map = unsafe { Mmap::map(&file)? };
+        let ImageView {
+            settings,
+            dictionary,
+            index,
+        } = ImageView::read(&map)?;
+        let total_count = dictionary.counts.iter().map(i64::from).sum();
+        let longest_word = dictionary.words.iter().map(str::len).max().unwrap_or(0);
+        let dictionary = dictionary.rebind(&[]);
+        let index = index.rebind(&[]);
+        let mapped = MappedDictionary {
+            map,
+            dictionary,
+            index,
+            total_count,
+            longest_word,
+        };
+        Ok((mapped, settings))
+    }
+
+    fn dictionary(&self) -> DictionaryView<'_> {
+        self.dictionary.rebind(&self.map)
+    }
+
+    fn index(&self) -> IndexView<'_> {
+        self.index.rebind(&self.map)
+    }
+
+    /// The image the dictionary was mapped from.
+    pub fn bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    pub fn total_count(&self) -> i64 {
+        self.total_count
+    }
+
+    pub fn longest_word(&self) -> usize {
+        self.longest_word
+    }
+
+    pub fn word(&self, id: u32) -> &str {
+        self.dictionary().words.get(id as usize)
+    }
+
+    pub fn count(&self, key: &str) -> Option<i32> {
+        let dictionary = self.dictionary();
+        let id = match self.index() {
+            IndexView::Automaton(trie) => trie.find(key),
+            IndexView::SymmetricDelete(index) => {
+                index.find(key, |id| dictionary.words.get(id as usize))
+            }
+        }?;
+        Some(dictionary.counts.get(id as usize))
+    }
+
+    pub fn cased(&self, key: &str) -> Option<&str> {
+        let dictionary = self.dictionary();
+        let i = dictionary.cased_keys.binary_search(key)?;
+        Some(dictionary.cased_words.get(i))
+    }
+
+    pub fn lookup(
+        &self,
+        builder: &LevenshteinAutomatonBuilder,
+        word: &str,
+        distance: u8,
+    ) -> Vec<(u32, u8)> {
+        match self.index() {
+            IndexView::Automaton(trie) => {
+                let mut matches = trie.search(&builder.build_dfa(word));
+                matches.retain(|&(_, d)| d <= distance);
+                matches
+            }
+            IndexView::SymmetricDelete(index) => {
+                let words = self.dictionary().words;
+                index.search(word, distance as usize, |id| words.get(id as usize))
+            }
+        }
+    }
+
+    pub fn ngram_score<F>(&self, context: &[&str], word: &str, unigram: F, total: i64) -> f64
+    where
+        F: Fn(&str) -> i64,
+    {
+        self.dictionary()
+            .ngrams
+            .score(context, word, unigram, total)
+    }
+
+    /// Copy the dictionary and index into memory, to modify them.
+    pub fn materialize(&self, case_sensitive: bool) -> (WordFrequency, Index) {
+        (
+            WordFrequency::from_view(self.dictionary(), case_sensitive),
+            self.index().to_index(),
+        )
+    }
+}
@@ -0,0 +1,236 @@
+//! Lookup of inflected words on Hunspell stems and affix rules, without expanding every form.
+//!
+//! For languages with rich inflection, such as Latvian or Finnish, expanding a `.dic` file
+//! multiplies the word list many times over. A [`Morphology`] keeps the stems and recognizes
+//! a word by stripping the affixes it may end or start with and checking the stem it is left
+//! with. Suggestions are found the same way: the stripped word is matched against the stems,
+//! and the close stems are inflected back, so a correction keeps the inflection of the word.
+
+use crate::compression;
+use crate::edit_distance;
+use crate::error::BuildError;
+use crate::hunspell::{AffixClass, AffixRule, Affixes, Flag};
+use crate::trie::Trie;
+use levenshtein_automata::LevenshteinAutomatonBuilder;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::Path;
+
+/// The stems of a Hunspell `.dic` file with the affix rules of its `.aff` file.
+#[derive(Clone)]
+pub struct Morphology {
+    affixes: Affixes,
+    /// Unique stems, in the order of their ids in `trie`.
+    stems: Vec<String>,
+    /// The flags of each stem. A stem listed several times has several sets of flags.
+    flags: Vec<Vec<Vec<Flag>>>,
+    ids: HashMap<String, u32>,
+    forbidden: HashSet<String>,
+    trie: Trie,
+    count: i32,
+    longest_word: usize,
+}
+
+impl Morphology {
+    /// Read the stems of a `.dic` file. Stems are lowercased unless `case_sensitive`.
+    pub fn from_reader<R: io::Read>(
+        affixes: Affixes,
+        reader: R,
+        case_sensitive: bool,
+    ) -> Result<Morphology, BuildError> {
+        let mut stems: Vec<String> = vec![];
+        let mut flags: Vec<Vec<Vec<Flag>>> = vec![];
+        let mut ids = HashMap::new();
+        let mut forbidden = HashSet::new();
+        affixes.read_dic(reader, |stem, stem_flags| {
+            let stem = if case_sensitive {
+                stem
+            } else {
+                stem.to_lowercase()
+            };
+            if affixes.is_forbidden(&stem_flags) {
+                forbidden.insert(stem);
+                return;
+            }
+            let id = *ids.entry(stem.clone()).or_insert_with(|| {
+                stems.push(stem);
+                flags.push(vec![]);
+                stems.len() as u32 - 1
+            });
+            flags[id as usize].push(stem_flags);
+        })?;
+
+        // The longest form has the longest prefix and two of the longest suffixes.
+        let longest_add = |classes: &HashMap<Flag, AffixClass>| {
+            classes
+                .values()
+                .flat_map(|class| class.rules.iter())
+                .map(|rule| rule.add.len())
+                .max()
+                .unwrap_or(0)
+        };
+        let longest_word = stems.iter().map(String::len).max().unwrap_or(0)
+            + longest_add(&affixes.prefixes)
+            + 2 * longest_add(&affixes.suffixes);
+        Ok(Morphology {
+            trie: Trie::from_words(&stems),
+            affixes,
+            stems,
+            flags,
+            ids,
+            forbidden,
+            count: 1,
+            longest_word,
+        })
+    }
+
+    pub fn from_file<P: AsRef<Path>>(
+        affixes: Affixes,
+        path: P,
+        case_sensitive: bool,
+    ) -> Result<Morphology, BuildError> {
+        Morphology::from_reader(affixes, compression::open(path)?, case_sensitive)
+    }
+
+    /// Set the count given to every recognized word, 1 by default.
+    pub fn with_count(mut self, count: i32) -> Morphology {
+        self.count = count;
+        self
+    }
+
+    pub fn count(&self) -> i32 {
+        self.count
+    }
+
+    pub fn affixes(&self) -> &Affixes {
+        &self.affixes
+    }
+
+    /// The stems that are words on their own, with the count of the morphology.
+    pub fn words(&self) -> HashMap<String, i32> {
+        self.stems
+            .iter()
+            .zip(self.flags.iter())
+            .filter(|(_, flags)| flags.iter().any(|flags| self.affixes.is_standalone(flags)))
+            .map(|(stem, _)| (stem.clone(), self.count))
+            .collect()
+    }
+
+    /// An upper bound on the length in bytes of the recognized words.
+    pub fn longest_word(&self) -> usize {
+        self.longest_word
+    }
+
+    /// The stems `word` is a form of, itself included if it is a stem.
+    pub fn stems(&self, word: &str) -> Vec<&str> {
+        if self.forbidden.contains(word) {
+            return vec![];
+        }
+        self.decompositions(word)
+            .iter()
+            .filter_map(|stem| self.ids.get(stem))
+            .filter(|&&id| self.forms(id).any(|form| form == word))
+            .map(|&id| self.stems[id as usize].as_str())
+            .collect()
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        !self.stems(word).is_empty()
+    }
+
+    /// The forms within `distance` of `word`, with their distance. The stems close to every
+    /// way of stripping affixes from `word` are inflected back, so the forms with the same
+    /// affixes as `word` come out closest.
+    pub(crate) fn candidates(
+        &self,
+        builder: &LevenshteinAutomatonBuilder,
+        word: &str,
+        distance: u8,
+    ) -> Vec<(String, u8)> {
+        let mut ids = HashSet::new();
+        for stem in self.decompositions(word) {
+            for (id, d) in self.trie.search(&builder.build_dfa(&stem)) {
+                if d <= distance {
+                    ids.insert(id);
+                }
+            }
+        }
+        let mut candidates: HashMap<String, u8> = HashMap::new();
+        for id in ids {
+            for form in self.forms(id) {
+                if self.forbidden.contains(&form) {
+                    continue;
+                }
+                if let Some(d) = edit_distance(word, &form, distance as usize) {
+                    let d = d as u8;
+                    candidates
+                        .entry(form)
+                        .and_modify(|best| *best = (*best).min(d))
+                        .or_insert(d);
+                }
+            }
+        }
+        let mut candidates: Vec<(String, u8)> = candidates.into_iter().collect();
+        candidates.sort_unstable();
+        candidates
+    }
+
+    /// Every form of the stem with id `id`.
+    fn forms(&self, id: u32) -> impl Iterator<Item = String> + '_ {
+        let stem = &self.stems[id as usize];
+        self.flags[id as usize]
+            .iter()
+            .flat_map(move |flags| self.affixes.expand(stem, flags))
+    }
+
+    /// The stems `word` may come from, found by undoing a prefix, one or two suffixes, or a
+    /// prefix and a suffix. The stems are not checked against the dictionary or the rules.
+    fn decompositions(&self, word: &str) -> Vec<String> {
+        let mut stems = vec![word.to_string()];
+        let mut prefixed = vec![];
+        for (_, rule) in rules(&self.affixes.prefixes) {
+            if let Some(stem) = unprefix(word, rule) {
+                prefixed.push(stem);
+            }
+        }
+        for (outer_flag, outer) in rules(&self.affixes.suffixes) {
+            let Some(stem) = unsuffix(word, outer) else {
+                continue;
+            };
+            // An inner suffix allows the outer one in its continuation classes.
+            for (_, inner) in rules(&self.affixes.suffixes) {
+                if inner.continuation.contains(&outer_flag) {
+                    stems.extend(unsuffix(&stem, inner));
+                }
+            }
+            stems.push(stem);
+        }
+        for word in prefixed {
+            for (_, rule) in rules(&self.affixes.suffixes) {
+                stems.extend(unsuffix(&word, rule));
+            }
+            stems.push(word);
+        }
+        let mut seen = HashSet::new();
+        stems.retain(|stem| seen.insert(stem.clone()));
+        stems
+    }
+}
+
+fn rules(classes: &HashMap<Flag, AffixClass>) -> impl Iterator<Item = (Flag, &AffixRule)> {
+    classes
+        .iter()
+        .flat_map(|(&flag, class)| class.rules.iter().map(move |rule| (flag, rule)))
+}
+
+/// Undo `rule` as a prefix of `word`.
+fn unprefix(word: &str, rule: &AffixRule) -> Option<String> {
+    let rest = word.strip_prefix(rule.add.as_str())?;
+    (!rest.is_empty()).then(|| format!("{}{}", rule.strip, rest))
+}
+
+/// Undo `rule` as a suffix of `word`.
+fn unsuffix(word: &str, rule: &AffixRule) -> Option<String> {
+    let rest = word.strip_suffix(rule.add.as_str())?;
+    (!rest.is_empty()).then(|| format!("{}{}", rest, rule.strip))
+}
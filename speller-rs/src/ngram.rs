@@ -0,0 +1,161 @@
+use crate::error::BuildError;
+use crate::image::{Array, ImageReader, ImageWriter, Strings};
+use std::collections::HashMap;
+use std::io;
+use std::io::BufRead;
+
+/// Weight applied each time the model backs off to a shorter n-gram (stupid backoff).
+const BACKOFF: f64 = 0.4;
+
+/// Bigram and trigram counts, keyed by their words joined with a space.
+#[derive(Clone, Default)]
+pub(crate) struct NgramModel {
+    bigrams: HashMap<String, i64>,
+    trigrams: HashMap<String, i64>,
+}
+
+impl NgramModel {
+    /// Read tab-separated lines of two or three words followed by their count. Counts of a
+    /// repeated n-gram are added up. Counts must be positive.
+    pub fn load<R: io::Read>(&mut self, reader: R, case_sensitive: bool) -> Result<(), BuildError> {
+        let reader = io::BufReader::new(reader);
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let parts: Vec<&str> = line.split('\t').collect();
+            let (count, words) = parts.split_last().ok_or(BuildError::TXTIndexError)?;
+            let count: i64 = count
+                .trim()
+                .parse()
+                .map_err(|_| BuildError::ParseCountError)?;
+            // Scores are compared as logarithms, so a count of 0 or less would poison them.
+            if count <= 0 {
+                return Err(BuildError::ParseCountError);
+            }
+            let key = words.join(" ");
+            let key = if case_sensitive {
+                key
+            } else {
+                key.to_lowercase()
+            };
+            let ngrams = match words.len() {
+                2 => &mut self.bigrams,
+                3 => &mut self.trigrams,
+                _ => return Err(BuildError::TXTIndexError),
+            };
+            *ngrams.entry(key).or_insert(0) += count;
+        }
+        Ok(())
+    }
+
+    /// Stupid-backoff score of `word` following `context` (at most the two previous words),
+    /// down to `unigram(word) / total`. `unigram` returns the dictionary count of a word.
+    pub fn score<F>(&self, context: &[&str], word: &str, unigram: F, total: i64) -> f64
+    where
+        F: Fn(&str) -> i64,
+    {
+        let bigram = |key: &str| self.bigrams.get(key).copied();
+        let trigram = |key: &str| self.trigrams.get(key).copied();
+        score(context, word, bigram, trigram, unigram, total)
+    }
+
+    /// Write the bigrams, then the trigrams, as sorted keys followed by their counts.
+    pub fn write_image(&self, image: &mut ImageWriter) {
+        for ngrams in [&self.bigrams, &self.trigrams] {
+            let mut ngrams: Vec<(&String, &i64)> = ngrams.iter().collect();
+            ngrams.sort();
+            image.strings(ngrams.iter().map(|(key, _)| key));
+            image.array(ngrams.iter().map(|(_, &count)| count));
+        }
+    }
+}
+
+/// N-grams written by [`NgramModel::write_image`], looked up in place.
+#[derive(Clone, Copy)]
+pub(crate) struct NgramView<'a> {
+    bigrams: (Strings<'a>, Array<'a, i64>),
+    trigrams: (Strings<'a>, Array<'a, i64>),
+}
+
+impl<'a> NgramView<'a> {
+    pub fn read(image: &mut ImageReader<'a>) -> Result<NgramView<'a>, BuildError> {
+        let mut read = || -> Result<_, BuildError> {
+            let (keys, counts) = (image.strings()?, image.array()?);
+            if keys.len() != counts.len() {
+                return Err(BuildError::InvalidImage);
+            }
+            Ok((keys, counts))
+        };
+        Ok(NgramView {
+            bigrams: read()?,
+            trigrams: read()?,
+        })
+    }
+
+    pub fn score<F>(&self, context: &[&str], word: &str, unigram: F, total: i64) -> f64
+    where
+        F: Fn(&str) -> i64,
+    {
+        let get = |(keys, counts): (Strings, Array<i64>), key: &str| {
+            keys.binary_search(key).map(|i| counts.get(i))
+        };
+        let bigram = |key: &str| get(self.bigrams, key);
+        let trigram = |key: &str| get(self.trigrams, key);
+        score(context, word, bigram, trigram, unigram, total)
+    }
+
+    pub fn to_model(self) -> NgramModel {
+        let collect = |(keys, counts): (Strings, Array<i64>)| {
+            keys.iter().map(str::to_string).zip(counts.iter()).collect()
+        };
+        NgramModel {
+            bigrams: collect(self.bigrams),
+            trigrams: collect(self.trigrams),
+        }
+    }
+
+    pub fn rebind<'b>(&self, image: &'b [u8]) -> NgramView<'b> {
+        let (keys, counts) = self.bigrams;
+        let bigrams = (keys.rebind(image), counts.rebind(image));
+        let (keys, counts) = self.trigrams;
+        let trigrams = (keys.rebind(image), counts.rebind(image));
+        NgramView { bigrams, trigrams }
+    }
+}
+
+/// Stupid-backoff score of `word` following `context`, with `bigram` and `trigram` returning
+/// the count of an n-gram key.
+fn score<B, T, F>(
+    context: &[&str],
+    word: &str,
+    bigram: B,
+    trigram: T,
+    unigram: F,
+    total: i64,
+) -> f64
+where
+    B: Fn(&str) -> Option<i64>,
+    T: Fn(&str) -> Option<i64>,
+    F: Fn(&str) -> i64,
+{
+    let mut weight = 1.0;
+    if let [.., first, second] = context {
+        let count = trigram(&format!("{first} {second} {word}"));
+        let history = bigram(&format!("{first} {second}"));
+        if let (Some(count), Some(history)) = (count, history) {
+            return count as f64 / history as f64;
+        }
+        weight *= BACKOFF;
+    }
+    if let Some(previous) = context.last() {
+        let count = bigram(&format!("{previous} {word}"));
+        let history = unigram(previous);
+        if let (Some(count), true) = (count, history > 0) {
+            return weight * count as f64 / history as f64;
+        }
+        weight *= BACKOFF;
+    }
+    weight * unigram(word).max(1) as f64 / total.max(1) as f64
+}
@@ -1,12 +1,84 @@
 use crate::error::BuildError;
-use std::collections::HashMap;
+use crate::hunspell::Affixes;
+use std::collections::{HashMap, HashSet};
 use std::io;
 use std::io::BufRead;
+use std::path::Path;
 
 pub trait WordLoader {
     fn load_words<R: io::Read>(&mut self, reader: R) -> Result<HashMap<String, i32>, BuildError>;
 }
 
+/// The format of a dictionary, for input that has no file extension to infer it from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Format {
+    #[cfg(feature = "serde_json")]
+    Json,
+    #[cfg(feature = "csv")]
+    Csv,
+    #[cfg(feature = "csv")]
+    Tsv,
+    Text,
+    /// One word per line without counts, such as `/usr/share/dict/words`. See
+    /// [`TextWordLoader::word_list`].
+    WordList,
+    /// The loader registered for this extension with
+    /// [`SpellerBuilder::register_loader`](crate::SpellerBuilder::register_loader).
+    Extension(String),
+}
+
+impl Format {
+    /// The built-in format of files with `extension`, or else the loader registered for it.
+    pub fn from_extension(extension: &str) -> Format {
+        match extension {
+            #[cfg(feature = "serde_json")]
+            "json" => Format::Json,
+            #[cfg(feature = "csv")]
+            "csv" => Format::Csv,
+            #[cfg(feature = "csv")]
+            "tsv" => Format::Tsv,
+            "txt" => Format::Text,
+            // Word lists such as `/usr/share/dict/words` usually have no extension.
+            "" => Format::WordList,
+            extension => Format::Extension(extension.to_string()),
+        }
+    }
+}
+
+/// A loader that hands over words one at a time as they are read, so a large file is never
+/// held in memory as a whole. See [`WordFrequency::load_stream`](crate::WordFrequency::load_stream).
+pub trait StreamingWordLoader {
+    /// Call `word` with each word and count read from `reader`, in file order.
+    fn stream_words<R, F>(&mut self, reader: R, word: F) -> Result<(), BuildError>
+    where
+        R: io::Read,
+        F: FnMut(String, i32);
+}
+
+/// Limits applied to each file while it is streamed: words below the minimum count are
+/// dropped, and only the `top_n` most frequent of the others are kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Cutoff {
+    pub min_count: Option<i32>,
+    pub top_n: Option<usize>,
+}
+
+impl Cutoff {
+    pub fn new() -> Cutoff {
+        Cutoff::default()
+    }
+
+    pub fn with_min_count(mut self, min_count: i32) -> Cutoff {
+        self.min_count = Some(min_count);
+        self
+    }
+
+    pub fn with_top_n(mut self, top_n: usize) -> Cutoff {
+        self.top_n = Some(top_n);
+        self
+    }
+}
+
 #[cfg(feature = "serde_json")]
 pub struct JsonWordLoader {}
 
@@ -25,6 +97,41 @@ impl WordLoader for JsonWordLoader {
     }
 }
 
+#[cfg(feature = "serde_json")]
+impl StreamingWordLoader for JsonWordLoader {
+    fn stream_words<R, F>(&mut self, reader: R, word: F) -> Result<(), BuildError>
+    where
+        R: io::Read,
+        F: FnMut(String, i32),
+    {
+        // Visits the entries of the top-level object without collecting them.
+        struct Entries<F>(F);
+
+        impl<'de, F: FnMut(String, i32)> serde::de::Visitor<'de> for Entries<F> {
+            type Value = ();
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a map of words to counts")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                mut self,
+                mut map: A,
+            ) -> Result<(), A::Error> {
+                while let Some((word, count)) = map.next_entry::<String, i32>()? {
+                    (self.0)(word, count);
+                }
+                Ok(())
+            }
+        }
+
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        serde::Deserializer::deserialize_map(&mut deserializer, Entries(word))?;
+        deserializer.end()?;
+        Ok(())
+    }
+}
+
 #[cfg(feature = "serde_json")]
 impl Default for JsonWordLoader {
     fn default() -> Self {
@@ -83,24 +190,61 @@ impl Default for CsvWordLoader {
 impl WordLoader for CsvWordLoader {
     fn load_words<R: io::Read>(&mut self, reader: R) -> Result<HashMap<String, i32>, BuildError> {
         let mut data = HashMap::new();
+        self.stream_words(reader, |word, count| {
+            data.insert(word, count);
+        })?;
+        Ok(data)
+    }
+}
+
+#[cfg(feature = "csv")]
+impl StreamingWordLoader for CsvWordLoader {
+    fn stream_words<R, F>(&mut self, reader: R, mut word: F) -> Result<(), BuildError>
+    where
+        R: io::Read,
+        F: FnMut(String, i32),
+    {
         let mut rdr = csv::ReaderBuilder::new()
             .has_headers(self.has_headers)
             .delimiter(self.delimiter)
             .from_reader(reader);
         for result in rdr.records() {
             let record = result?;
-            let word = record
+            let text = record
                 .get(self.word_index)
                 .ok_or(BuildError::CSVIndexError)?;
             let count = record
                 .get(self.count_index)
                 .ok_or(BuildError::CSVIndexError)?;
-            data.insert(
-                word.to_string(),
+            word(
+                text.to_string(),
                 count.parse().map_err(|_| BuildError::ParseCountError)?,
             );
         }
-        Ok(data)
+        Ok(())
+    }
+}
+
+/// A Unicode normalization form, applied to words as they are loaded.
+#[cfg(feature = "unicode-normalization")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    Nfc,
+    Nfd,
+    Nfkc,
+    Nfkd,
+}
+
+#[cfg(feature = "unicode-normalization")]
+impl Normalization {
+    pub fn normalize(self, word: &str) -> String {
+        use unicode_normalization::UnicodeNormalization;
+        match self {
+            Normalization::Nfc => word.nfc().collect(),
+            Normalization::Nfd => word.nfd().collect(),
+            Normalization::Nfkc => word.nfkc().collect(),
+            Normalization::Nfkd => word.nfkd().collect(),
+        }
     }
 }
 
@@ -109,6 +253,11 @@ pub struct TextWordLoader {
     count_index: usize,
     delimiter: u8,
     has_headers: bool,
+    default_count: Option<i32>,
+    word_list: bool,
+    dedup: bool,
+    #[cfg(feature = "unicode-normalization")]
+    normalization: Option<Normalization>,
 }
 
 impl TextWordLoader {
@@ -118,6 +267,22 @@ impl TextWordLoader {
             count_index: 1,
             delimiter: b' ',
             has_headers: false,
+            default_count: None,
+            word_list: false,
+            dedup: false,
+            #[cfg(feature = "unicode-normalization")]
+            normalization: None,
+        }
+    }
+
+    /// A loader for plain word lists, one word per line, each with a count of 1 or the one
+    /// given to [`TextWordLoader::with_default_count`]. The whole trimmed line is the word, so
+    /// entries such as `ad hoc` are kept as they are. Blank lines and lines starting with `#`
+    /// are skipped.
+    pub fn word_list() -> TextWordLoader {
+        TextWordLoader {
+            word_list: true,
+            ..TextWordLoader::new().with_default_count(1)
         }
     }
 
@@ -140,6 +305,28 @@ impl TextWordLoader {
         self.has_headers = has_headers;
         self
     }
+
+    /// Give `count` to the words of lines without a count column instead of failing with
+    /// [`BuildError::TXTIndexError`]. Lines starting with `#` are then skipped as comments.
+    pub fn with_default_count(mut self, count: i32) -> TextWordLoader {
+        self.default_count = Some(count);
+        self
+    }
+
+    /// Keep only the first line of a word listed several times. Otherwise its last line wins,
+    /// as with the other loaders.
+    pub fn with_dedup(mut self, dedup: bool) -> TextWordLoader {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Normalize every word to `normalization`, so that words written with combining marks
+    /// and with precomposed characters are the same word.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn with_normalization(mut self, normalization: Normalization) -> TextWordLoader {
+        self.normalization = Some(normalization);
+        self
+    }
 }
 
 impl Default for TextWordLoader {
@@ -151,20 +338,104 @@ impl Default for TextWordLoader {
 impl WordLoader for TextWordLoader {
     fn load_words<R: io::Read>(&mut self, reader: R) -> Result<HashMap<String, i32>, BuildError> {
         let mut data = HashMap::new();
+        self.stream_words(reader, |word, count| {
+            data.insert(word, count);
+        })?;
+        Ok(data)
+    }
+}
+
+impl StreamingWordLoader for TextWordLoader {
+    fn stream_words<R, F>(&mut self, reader: R, mut word: F) -> Result<(), BuildError>
+    where
+        R: io::Read,
+        F: FnMut(String, i32),
+    {
         let reader = io::BufReader::new(reader);
+        let mut seen = HashSet::new();
         for line in reader.lines() {
             let line = line?;
-            let parts: Vec<&str> = line.split(self.delimiter as char).collect();
-            let word = parts
-                .get(self.word_index)
-                .ok_or(BuildError::TXTIndexError)?;
-            let count = parts
-                .get(self.count_index)
-                .ok_or(BuildError::TXTIndexError)?;
-            data.insert(
-                word.to_string(),
-                count.parse().map_err(|_| BuildError::ParseCountError)?,
-            );
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+            if self.default_count.is_some() && line.starts_with('#') {
+                continue;
+            }
+            let (text, count) = if self.word_list {
+                (line.trim_start(), self.default_count.unwrap_or(1))
+            } else {
+                let parts: Vec<&str> = line.split(self.delimiter as char).collect();
+                let text = parts
+                    .get(self.word_index)
+                    .ok_or(BuildError::TXTIndexError)?;
+                let count = match (parts.get(self.count_index), self.default_count) {
+                    (Some(count), _) => count.parse().map_err(|_| BuildError::ParseCountError)?,
+                    (None, Some(count)) => count,
+                    (None, None) => return Err(BuildError::TXTIndexError),
+                };
+                (*text, count)
+            };
+            #[cfg(feature = "unicode-normalization")]
+            let text = match self.normalization {
+                Some(normalization) => normalization.normalize(text),
+                None => text.to_string(),
+            };
+            #[cfg(not(feature = "unicode-normalization"))]
+            let text = text.to_string();
+            if self.dedup && !seen.insert(text.clone()) {
+                continue;
+            }
+            word(text, count);
+        }
+        Ok(())
+    }
+}
+
+/// Reads a Hunspell `.dic` file and expands each stem into its surface forms with the rules
+/// of the matching `.aff` file. Hunspell dictionaries have no frequencies, so every form gets
+/// the same count, 1 by default.
+pub struct HunspellWordLoader {
+    affixes: Affixes,
+    count: i32,
+}
+
+impl HunspellWordLoader {
+    pub fn new(affixes: Affixes) -> HunspellWordLoader {
+        HunspellWordLoader { affixes, count: 1 }
+    }
+
+    pub fn from_aff_file<P: AsRef<Path>>(path: P) -> Result<HunspellWordLoader, BuildError> {
+        Ok(HunspellWordLoader::new(Affixes::from_file(path)?))
+    }
+
+    pub fn with_count(mut self, count: i32) -> HunspellWordLoader {
+        self.count = count;
+        self
+    }
+
+    pub fn affixes(&self) -> &Affixes {
+        &self.affixes
+    }
+}
+
+impl WordLoader for HunspellWordLoader {
+    fn load_words<R: io::Read>(&mut self, reader: R) -> Result<HashMap<String, i32>, BuildError> {
+        let mut data = HashMap::new();
+        let mut forbidden = vec![];
+        let affixes = &self.affixes;
+        affixes.read_dic(reader, |stem, flags| {
+            if affixes.is_forbidden(&flags) {
+                forbidden.push(stem);
+                return;
+            }
+            for form in affixes.expand(&stem, &flags) {
+                data.insert(form, self.count);
+            }
+        })?;
+        // A forbidden word stays out even when another stem expands to it.
+        for word in forbidden {
+            data.remove(&word);
         }
         Ok(data)
     }
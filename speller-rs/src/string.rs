@@ -6,3 +6,67 @@ lazy_static!(
     pub static ref PUNCTUATION: HashSet<char> =
     r#"!"$%&'()*+,-./:;<=>?@[\]^_`{|}~#"#.chars().collect();
 );
+
+/// Capitalization of a word, used to give a correction the same shape as the word it replaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CasePattern {
+    Lower,
+    Title,
+    Upper,
+    Mixed,
+}
+
+impl CasePattern {
+    pub fn of(word: &str) -> CasePattern {
+        let mut letters = word.chars().filter(|c| c.is_alphabetic());
+        let Some(first) = letters.next() else {
+            return CasePattern::Lower;
+        };
+        let rest: Vec<char> = letters.collect();
+        if first.is_lowercase() {
+            if rest.iter().all(|c| !c.is_uppercase()) {
+                CasePattern::Lower
+            } else {
+                CasePattern::Mixed
+            }
+        } else if rest.iter().all(|c| !c.is_lowercase()) && !rest.is_empty() {
+            CasePattern::Upper
+        } else if rest.iter().all(|c| !c.is_uppercase()) {
+            CasePattern::Title
+        } else {
+            CasePattern::Mixed
+        }
+    }
+
+    /// Reshape `word` after `pattern`, whose shape is `self`. A mixed pattern is copied char
+    /// by char when both words have the same length, and otherwise leaves `word` as is.
+    pub fn apply_from(self, pattern: &str, word: &str) -> String {
+        match self {
+            CasePattern::Lower => word.to_string(),
+            CasePattern::Mixed => {
+                if pattern.chars().count() != word.chars().count() {
+                    return word.to_string();
+                }
+                pattern
+                    .chars()
+                    .zip(word.chars())
+                    .flat_map(|(p, c)| {
+                        if p.is_uppercase() {
+                            c.to_uppercase().collect::<Vec<_>>()
+                        } else {
+                            c.to_lowercase().collect()
+                        }
+                    })
+                    .collect()
+            }
+            CasePattern::Upper => word.to_uppercase(),
+            CasePattern::Title => {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars).collect(),
+                    None => String::new(),
+                }
+            }
+        }
+    }
+}
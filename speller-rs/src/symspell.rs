@@ -0,0 +1,232 @@
+use crate::edit_distance;
+use crate::error::BuildError;
+use crate::image::{Array, ImageReader, ImageWriter};
+use std::collections::{HashMap, HashSet};
+
+/// Symmetric delete index: every string reachable from a dictionary word by deleting up to
+/// `max_distance` characters points back to that word. Two words within distance `d` always
+/// share such a delete, so a lookup only has to hash the deletes of the query.
+///
+/// Deletes are stored by hash in a sorted table rather than as strings, which keeps the index
+/// compact. A hash collision only adds a candidate that the final distance check rejects.
+/// Words added after the build go to a small overflow map, and removed ones are tombstoned.
+#[derive(Clone)]
+pub(crate) struct DeleteIndex {
+    max_distance: usize,
+    hashes: Vec<u64>,
+    ids: Vec<u32>,
+    added: HashMap<u64, Vec<u32>>,
+}
+
+const TOMBSTONE: u32 = u32::MAX;
+
+impl DeleteIndex {
+    pub fn from_words<S: AsRef<str>>(words: &[S], max_distance: usize) -> DeleteIndex {
+        let mut entries: Vec<(u64, u32)> = vec![];
+        for (id, word) in words.iter().enumerate() {
+            for delete in deletes(word.as_ref(), max_distance) {
+                entries.push((hash(&delete), id as u32));
+            }
+        }
+        entries.sort_unstable();
+        let (hashes, ids) = entries.into_iter().unzip();
+        DeleteIndex {
+            max_distance,
+            hashes,
+            ids,
+            added: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, word: &str, id: u32) {
+        for delete in deletes(word, self.max_distance) {
+            self.added.entry(hash(&delete)).or_default().push(id);
+        }
+    }
+
+    pub fn remove(&mut self, word: &str, id: u32) {
+        self.relabel(word, id, TOMBSTONE);
+    }
+
+    /// Point the entries of `word` from id `from` to id `to`.
+    pub fn relabel(&mut self, word: &str, from: u32, to: u32) {
+        for delete in deletes(word, self.max_distance) {
+            let hash = hash(&delete);
+            let range = self.range(hash);
+            let added = self.added.get_mut(&hash).into_iter().flatten();
+            for id in self.ids[range].iter_mut().chain(added) {
+                if *id == from {
+                    *id = to;
+                }
+            }
+        }
+    }
+
+    fn range(&self, hash: u64) -> std::ops::Range<usize> {
+        let start = self.hashes.partition_point(|&h| h < hash);
+        let end = self.hashes.partition_point(|&h| h <= hash);
+        start..end
+    }
+
+    /// Return the id and exact distance of every word within `max_distance` of `word`.
+    /// `words` returns the word of an id.
+    pub fn search<'w, W>(&self, word: &str, max_distance: usize, words: W) -> Vec<(u32, u8)>
+    where
+        W: Fn(u32) -> &'w str,
+    {
+        let candidates = |hash| {
+            let added = self.added.get(&hash).into_iter().flatten();
+            self.ids[self.range(hash)].iter().chain(added).copied()
+        };
+        search(word, max_distance.min(self.max_distance), candidates, words)
+    }
+
+    /// Write the index as its maximum distance followed by the sorted hashes and their ids.
+    /// Words added since the build are merged in and removed ones are dropped.
+    pub fn write_image(&self, image: &mut ImageWriter) {
+        let added = self
+            .added
+            .iter()
+            .flat_map(|(&hash, ids)| ids.iter().map(move |&id| (hash, id)));
+        let mut entries: Vec<(u64, u32)> = self
+            .hashes
+            .iter()
+            .copied()
+            .zip(self.ids.iter().copied())
+            .chain(added)
+            .filter(|&(_, id)| id != TOMBSTONE)
+            .collect();
+        entries.sort_unstable();
+        image.array([self.max_distance as u32]);
+        image.array(entries.iter().map(|&(hash, _)| hash));
+        image.array(entries.iter().map(|&(_, id)| id));
+    }
+}
+
+/// An index written by [`DeleteIndex::write_image`], searched in place.
+#[derive(Clone, Copy)]
+pub(crate) struct DeleteIndexView<'a> {
+    max_distance: usize,
+    hashes: Array<'a, u64>,
+    ids: Array<'a, u32>,
+}
+
+impl<'a> DeleteIndexView<'a> {
+    /// Read and check the sections of an index over a list of `words` words.
+    pub fn read(
+        image: &mut ImageReader<'a>,
+        words: usize,
+    ) -> Result<DeleteIndexView<'a>, BuildError> {
+        let max_distance = image.array::<u32>()?;
+        let view = DeleteIndexView {
+            max_distance: max_distance.iter().next().unwrap_or_default() as usize,
+            hashes: image.array()?,
+            ids: image.array()?,
+        };
+        if max_distance.len() != 1
+            || view.hashes.len() != view.ids.len()
+            || view.ids.iter().any(|id| id as usize >= words)
+            || view
+                .hashes
+                .iter()
+                .zip(view.hashes.iter().skip(1))
+                .any(|(a, b)| a > b)
+        {
+            return Err(BuildError::InvalidImage);
+        }
+        Ok(view)
+    }
+
+    fn candidates(&self, hash: u64) -> impl Iterator<Item = u32> + 'a {
+        let start = self.hashes.partition_point(|h| h < hash);
+        let end = self.hashes.partition_point(|h| h <= hash);
+        let ids = self.ids;
+        (start..end).map(move |i| ids.get(i))
+    }
+
+    /// The id of `word`, which is always one of its own deletes.
+    pub fn find<'w, W>(&self, word: &str, words: W) -> Option<u32>
+    where
+        W: Fn(u32) -> &'w str,
+    {
+        self.candidates(hash(word)).find(|&id| words(id) == word)
+    }
+
+    pub fn search<'w, W>(&self, word: &str, max_distance: usize, words: W) -> Vec<(u32, u8)>
+    where
+        W: Fn(u32) -> &'w str,
+    {
+        let max_distance = max_distance.min(self.max_distance);
+        search(word, max_distance, |hash| self.candidates(hash), words)
+    }
+
+    pub fn to_index(self) -> DeleteIndex {
+        DeleteIndex {
+            max_distance: self.max_distance,
+            hashes: self.hashes.iter().collect(),
+            ids: self.ids.iter().collect(),
+            added: HashMap::new(),
+        }
+    }
+
+    pub fn rebind<'b>(&self, image: &'b [u8]) -> DeleteIndexView<'b> {
+        DeleteIndexView {
+            max_distance: self.max_distance,
+            hashes: self.hashes.rebind(image),
+            ids: self.ids.rebind(image),
+        }
+    }
+}
+
+/// Check the distance to `word` of the ids that `candidates` returns for the hash of each
+/// of its deletes.
+fn search<'w, C, I, W>(word: &str, max_distance: usize, candidates: C, words: W) -> Vec<(u32, u8)>
+where
+    C: Fn(u64) -> I,
+    I: Iterator<Item = u32>,
+    W: Fn(u32) -> &'w str,
+{
+    let mut seen = HashSet::new();
+    let mut matches = vec![];
+    for delete in deletes(word, max_distance) {
+        for id in candidates(hash(&delete)) {
+            if id == TOMBSTONE || !seen.insert(id) {
+                continue;
+            }
+            if let Some(distance) = edit_distance(word, words(id), max_distance) {
+                matches.push((id, distance as u8));
+            }
+        }
+    }
+    matches
+}
+
+/// All strings obtained by deleting up to `max_distance` characters from `word`, including
+/// `word` itself.
+fn deletes(word: &str, max_distance: usize) -> HashSet<String> {
+    let mut result = HashSet::new();
+    let mut frontier = vec![word.chars().collect::<Vec<_>>()];
+    result.insert(word.to_string());
+    for _ in 0..max_distance {
+        let mut next = vec![];
+        for chars in frontier.iter() {
+            for i in 0..chars.len() {
+                let mut delete = chars.clone();
+                delete.remove(i);
+                if result.insert(delete.iter().collect()) {
+                    next.push(delete);
+                }
+            }
+        }
+        frontier = next;
+    }
+    result
+}
+
+/// 64-bit FNV-1a. Unlike the standard library hasher, it is guaranteed not to change between
+/// Rust releases, which saved images rely on.
+fn hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
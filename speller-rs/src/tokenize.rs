@@ -0,0 +1,148 @@
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Word,
+    Number,
+    Url,
+    Email,
+}
+
+/// A token of a text, with its position as byte and char offsets into the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub text: &'a str,
+    pub kind: TokenKind,
+    pub byte_span: Range<usize>,
+    pub char_span: Range<usize>,
+}
+
+const URL_PREFIXES: [&str; 4] = ["http://", "https://", "ftp://", "www."];
+
+/// Split `text` into words, numbers, URLs and emails. Whitespace and punctuation between
+/// tokens are skipped.
+///
+/// Words follow the Unicode word boundary rules, so apostrophes inside a word ("don't") are
+/// kept, and words joined by hyphens ("well-known") form a single token.
+pub fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut spans = vec![];
+    for (start, chunk) in chunks(text) {
+        let trimmed = chunk.trim_start_matches(is_leading_punctuation);
+        let offset = start + chunk.len() - trimmed.len();
+        let trimmed = trimmed.trim_end_matches(is_trailing_punctuation);
+        if is_url(trimmed) {
+            spans.push((offset..offset + trimmed.len(), TokenKind::Url));
+        } else if is_email(trimmed) {
+            spans.push((offset..offset + trimmed.len(), TokenKind::Email));
+        } else {
+            words(chunk, start, &mut spans);
+        }
+    }
+
+    let mut tokens = Vec::with_capacity(spans.len());
+    let (mut byte, mut char) = (0, 0);
+    for (span, kind) in spans {
+        char += text[byte..span.start].chars().count();
+        let token = &text[span.clone()];
+        let length = token.chars().count();
+        tokens.push(Token {
+            text: token,
+            kind,
+            char_span: char..char + length,
+            byte_span: span.clone(),
+        });
+        char += length;
+        byte = span.end;
+    }
+    tokens
+}
+
+/// Split `text` on whitespace, keeping the byte offset of each chunk.
+fn chunks(text: &str) -> Vec<(usize, &str)> {
+    let mut chunks = vec![];
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                chunks.push((s, &text[s..i]));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        chunks.push((s, &text[s..]));
+    }
+    chunks
+}
+
+/// Push the words and numbers of `chunk`, which starts at byte `offset` of the text.
+fn words(chunk: &str, offset: usize, spans: &mut Vec<(Range<usize>, TokenKind)>) {
+    let kind = |is_number| {
+        if is_number {
+            TokenKind::Number
+        } else {
+            TokenKind::Word
+        }
+    };
+    let mut current: Option<(Range<usize>, bool)> = None;
+    for (start, segment) in chunk.split_word_bound_indices() {
+        if !segment.chars().next().is_some_and(char::is_alphanumeric) {
+            continue;
+        }
+        let is_number = segment
+            .chars()
+            .all(|c| c.is_numeric() || c == '.' || c == ',');
+        // Merge "well", "-", "known" into "well-known".
+        if let Some((span, false)) = &mut current {
+            let end = span.end - offset;
+            if !is_number && end + 1 == start && &chunk[end..start] == "-" {
+                span.end = offset + start + segment.len();
+                continue;
+            }
+        }
+        let next = (offset + start..offset + start + segment.len(), is_number);
+        if let Some((span, is_number)) = current.replace(next) {
+            spans.push((span, kind(is_number)));
+        }
+    }
+    if let Some((span, is_number)) = current {
+        spans.push((span, kind(is_number)));
+    }
+}
+
+fn is_leading_punctuation(c: char) -> bool {
+    matches!(c, '(' | '[' | '{' | '<' | '"' | '\'' | '“' | '‘' | '«')
+}
+
+fn is_trailing_punctuation(c: char) -> bool {
+    matches!(
+        c,
+        '.' | ',' | ';' | ':' | '!' | '?' | ')' | ']' | '}' | '>' | '"' | '\'' | '”' | '’' | '»'
+    )
+}
+
+fn is_url(chunk: &str) -> bool {
+    URL_PREFIXES.iter().any(|prefix| {
+        chunk.len() > prefix.len()
+            && chunk.is_char_boundary(prefix.len())
+            && chunk[..prefix.len()].eq_ignore_ascii_case(prefix)
+    })
+}
+
+fn is_email(chunk: &str) -> bool {
+    let Some((local, domain)) = chunk.split_once('@') else {
+        return false;
+    };
+    let valid = |c: char| c.is_alphanumeric() || matches!(c, '.' | '_' | '-' | '+');
+    !local.is_empty()
+        && local.chars().all(valid)
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && domain
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '.' || c == '-')
+}
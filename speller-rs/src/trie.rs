@@ -0,0 +1,208 @@
+use crate::error::BuildError;
+use crate::image::{Array, ImageReader, ImageWriter};
+use levenshtein_automata::{Distance, DFA, SINK_STATE};
+
+/// Byte-wise prefix tree over the dictionary. Terminal nodes store the index of their
+/// word in `WordFrequency::list`.
+#[derive(Clone)]
+pub(crate) struct Trie {
+    nodes: Vec<Node>,
+}
+
+#[derive(Clone, Default)]
+struct Node {
+    children: Vec<(u8, u32)>,
+    word: Option<u32>,
+}
+
+impl Trie {
+    pub fn new() -> Trie {
+        Trie {
+            nodes: vec![Node::default()],
+        }
+    }
+
+    pub fn from_words<S: AsRef<str>>(words: &[S]) -> Trie {
+        let mut trie = Trie::new();
+        for (id, word) in words.iter().enumerate() {
+            trie.insert(word.as_ref(), id as u32);
+        }
+        trie
+    }
+
+    pub fn insert(&mut self, word: &str, id: u32) {
+        let mut node = 0;
+        for &byte in word.as_bytes() {
+            let next = self.nodes.len() as u32;
+            let children = &mut self.nodes[node].children;
+            node = match children.binary_search_by_key(&byte, |&(b, _)| b) {
+                Ok(i) => children[i].1,
+                Err(i) => {
+                    children.insert(i, (byte, next));
+                    self.nodes.push(Node::default());
+                    next
+                }
+            } as usize;
+        }
+        self.nodes[node].word = Some(id);
+    }
+
+    /// Unmark `word`, leaving its nodes in place.
+    pub fn remove(&mut self, word: &str) {
+        if let Some(node) = self.find(word) {
+            self.nodes[node].word = None;
+        }
+    }
+
+    fn find(&self, word: &str) -> Option<usize> {
+        let mut node = 0;
+        for &byte in word.as_bytes() {
+            let children = &self.nodes[node].children;
+            let i = children.binary_search_by_key(&byte, |&(b, _)| b).ok()?;
+            node = children[i].1 as usize;
+        }
+        Some(node)
+    }
+
+    /// Walk the trie in lockstep with `dfa` and return the id and distance of every word
+    /// the automaton accepts. Subtrees are pruned as soon as the automaton reaches its
+    /// sink state, so the cost depends on the explored prefixes, not on the dictionary size.
+    pub fn search(&self, dfa: &DFA) -> Vec<(u32, u8)> {
+        walk(
+            dfa,
+            |node| self.nodes[node as usize].children.iter().copied(),
+            |node| self.nodes[node as usize].word,
+        )
+    }
+
+    /// Write the trie as flat arrays: where the children of each node start (plus the end),
+    /// the byte and node of each child, and the word id of each node, `u32::MAX` for none.
+    pub fn write_image(&self, image: &mut ImageWriter) {
+        let mut starts = vec![0u32];
+        for node in self.nodes.iter() {
+            starts.push(starts[starts.len() - 1] + node.children.len() as u32);
+        }
+        let children = || self.nodes.iter().flat_map(|node| node.children.iter());
+        image.array(starts);
+        image.array(children().map(|&(byte, _)| byte));
+        image.array(children().map(|&(_, child)| child));
+        image.array(self.nodes.iter().map(|node| node.word.unwrap_or(u32::MAX)));
+    }
+}
+
+impl Default for Trie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Depth-first walk of a trie in lockstep with `dfa`, from node 0. `children` returns the
+/// byte and node of the children of a node, `word` the id of the word it ends, if any.
+fn walk<C, I, W>(dfa: &DFA, children: C, word: W) -> Vec<(u32, u8)>
+where
+    C: Fn(u32) -> I,
+    I: Iterator<Item = (u8, u32)>,
+    W: Fn(u32) -> Option<u32>,
+{
+    let mut matches = vec![];
+    let mut stack = vec![(0, dfa.initial_state())];
+    while let Some((node, state)) = stack.pop() {
+        if let (Some(id), Distance::Exact(distance)) = (word(node), dfa.distance(state)) {
+            matches.push((id, distance));
+        }
+        for (byte, child) in children(node) {
+            let next = dfa.transition(state, byte);
+            if next != SINK_STATE {
+                stack.push((child, next));
+            }
+        }
+    }
+    matches
+}
+
+/// A trie written by [`Trie::write_image`], searched in place.
+#[derive(Clone, Copy)]
+pub(crate) struct TrieView<'a> {
+    starts: Array<'a, u32>,
+    bytes: Array<'a, u8>,
+    children: Array<'a, u32>,
+    words: Array<'a, u32>,
+}
+
+impl<'a> TrieView<'a> {
+    /// Read and check the sections of a trie over a list of `words` words.
+    pub fn read(image: &mut ImageReader<'a>, words: usize) -> Result<TrieView<'a>, BuildError> {
+        let view = TrieView {
+            starts: image.array()?,
+            bytes: image.array()?,
+            children: image.array()?,
+            words: image.array()?,
+        };
+        let nodes = view.words.len();
+        if nodes == 0
+            || view.starts.len() != nodes + 1
+            || view.bytes.len() != view.children.len()
+            || view.starts.get(nodes) as usize != view.children.len()
+            || view
+                .starts
+                .iter()
+                .zip(view.starts.iter().skip(1))
+                .any(|(a, b)| a > b)
+            || view.children.iter().any(|child| child as usize >= nodes)
+            || view
+                .words
+                .iter()
+                .any(|id| id != u32::MAX && id as usize >= words)
+        {
+            return Err(BuildError::InvalidImage);
+        }
+        Ok(view)
+    }
+
+    fn children(&self, node: u32) -> impl Iterator<Item = (u8, u32)> + 'a {
+        let (bytes, children) = (self.bytes, self.children);
+        let start = self.starts.get(node as usize) as usize;
+        let end = self.starts.get(node as usize + 1) as usize;
+        (start..end).map(move |i| (bytes.get(i), children.get(i)))
+    }
+
+    fn word(&self, node: u32) -> Option<u32> {
+        let id = self.words.get(node as usize);
+        (id != u32::MAX).then_some(id)
+    }
+
+    /// The id of `word`, if it is in the trie.
+    pub fn find(&self, word: &str) -> Option<u32> {
+        let mut node = 0;
+        for byte in word.bytes() {
+            node = self
+                .children(node)
+                .find(|&(b, _)| b == byte)
+                .map(|(_, child)| child)?;
+        }
+        self.word(node)
+    }
+
+    pub fn search(&self, dfa: &DFA) -> Vec<(u32, u8)> {
+        walk(dfa, |node| self.children(node), |node| self.word(node))
+    }
+
+    pub fn to_trie(self) -> Trie {
+        let nodes = (0..self.words.len() as u32)
+            .map(|node| Node {
+                children: self.children(node).collect(),
+                word: self.word(node),
+            })
+            .collect();
+        Trie { nodes }
+    }
+
+    pub fn rebind<'b>(&self, image: &'b [u8]) -> TrieView<'b> {
+        TrieView {
+            starts: self.starts.rebind(image),
+            bytes: self.bytes.rebind(image),
+            children: self.children.rebind(image),
+            words: self.words.rebind(image),
+        }
+    }
+}
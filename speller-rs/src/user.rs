@@ -0,0 +1,232 @@
+use crate::error::BuildError;
+use crate::trie::Trie;
+use levenshtein_automata::DFA;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::mem;
+use std::path::Path;
+
+/// A writable layer of words stacked on the base dictionary of a [`Speller`](crate::Speller)
+/// with [`Speller::push_layer`](crate::Speller::push_layer). A layer can add words, override
+/// the count of base words and ban words so they are no longer accepted or suggested.
+///
+/// Layers are saved to their own file, one entry per line: `word count` adds a word or
+/// overrides its count, `!word` bans it. Blank lines and lines starting with `#` are skipped.
+#[derive(Clone)]
+pub struct UserDictionary {
+    case_sensitive: bool,
+    /// Normalized word to its spelling as added and its count.
+    words: HashMap<String, (String, i32)>,
+    /// Normalized word to its spelling as banned.
+    banned: HashMap<String, String>,
+    longest_word: usize,
+    /// The normalized words, indexed by their position in `keys` so candidates are found
+    /// with the Levenshtein automaton, as in the base dictionary.
+    index: Trie,
+    /// Every normalized word ever added, by id. Removed words are unmarked in `index`.
+    keys: Vec<String>,
+}
+
+impl std::fmt::Debug for UserDictionary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("UserDictionary")
+            .field("case_sensitive", &self.case_sensitive)
+            .field("words", &self.words)
+            .field("banned", &self.banned)
+            .finish()
+    }
+}
+
+impl PartialEq for UserDictionary {
+    fn eq(&self, other: &UserDictionary) -> bool {
+        self.case_sensitive == other.case_sensitive
+            && self.words == other.words
+            && self.banned == other.banned
+    }
+}
+
+impl Eq for UserDictionary {}
+
+impl UserDictionary {
+    pub fn new(case_sensitive: bool) -> UserDictionary {
+        UserDictionary {
+            case_sensitive,
+            words: HashMap::new(),
+            banned: HashMap::new(),
+            longest_word: 0,
+            index: Trie::new(),
+            keys: vec![],
+        }
+    }
+
+    /// Read a layer saved with [`UserDictionary::write_to`]. A word without a count gets
+    /// a count of 1.
+    pub fn from_reader<R: io::Read>(
+        reader: R,
+        case_sensitive: bool,
+    ) -> Result<UserDictionary, BuildError> {
+        let mut layer = UserDictionary::new(case_sensitive);
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(word) = line.strip_prefix('!') {
+                layer.ban_word(word.trim());
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let word = parts.next().ok_or(BuildError::TXTIndexError)?;
+            let count = match parts.next() {
+                Some(count) => count.parse().map_err(|_| BuildError::ParseCountError)?,
+                None => 1,
+            };
+            layer.add_word(word, count);
+        }
+        Ok(layer)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(
+        path: P,
+        case_sensitive: bool,
+    ) -> Result<UserDictionary, BuildError> {
+        UserDictionary::from_reader(File::open(path)?, case_sensitive)
+    }
+
+    /// Write the layer in the format read by [`UserDictionary::from_reader`], sorted by word.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut words: Vec<&(String, i32)> = self.words.values().collect();
+        words.sort();
+        for (word, count) in words {
+            writeln!(writer, "{word} {count}")?;
+        }
+        let mut banned: Vec<&String> = self.banned.values().collect();
+        banned.sort();
+        for word in banned {
+            writeln!(writer, "!{word}")?;
+        }
+        writer.flush()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+
+    /// Add `word` with `count`, replacing its count in the layers below. A banned word is
+    /// allowed again.
+    pub fn add_word(&mut self, word: &str, count: i32) {
+        let key = self.normalize(word);
+        if key.is_empty() {
+            return;
+        }
+        self.banned.remove(&key);
+        self.longest_word = self.longest_word.max(key.len());
+        if !self.words.contains_key(&key) {
+            self.index.insert(&key, self.keys.len() as u32);
+            self.keys.push(key.clone());
+        }
+        self.words.insert(key, (word.to_string(), count));
+    }
+
+    /// Hide `word` from the layers below: it is reported as misspelled and never suggested.
+    pub fn ban_word(&mut self, word: &str) {
+        let key = self.normalize(word);
+        if key.is_empty() {
+            return;
+        }
+        self.remove(&key);
+        self.banned.insert(key, word.to_string());
+    }
+
+    /// Forget what the layer says about `word`, whether added or banned. Return whether it
+    /// had an entry.
+    pub fn remove(&mut self, word: &str) -> bool {
+        let key = self.normalize(word);
+        let added = self.words.remove(&key).is_some();
+        if added {
+            self.index.remove(&key);
+        }
+        let removed = added | self.banned.remove(&key).is_some();
+        if key.len() == self.longest_word {
+            self.longest_word = self.words.keys().map(|word| word.len()).max().unwrap_or(0);
+        }
+        removed
+    }
+
+    /// The count of `word` if the layer adds it.
+    pub fn count(&self, word: &str) -> Option<i32> {
+        self.words
+            .get(&self.normalize(word))
+            .map(|&(_, count)| count)
+    }
+
+    pub fn is_banned(&self, word: &str) -> bool {
+        self.banned.contains_key(&self.normalize(word))
+    }
+
+    pub fn is_case_sensitive(&self) -> bool {
+        self.case_sensitive
+    }
+
+    /// What the layer says about the normalized `key`: `Some(Some(count))` if it adds the
+    /// word, `Some(None)` if it bans it and `None` if it leaves it to the layers below.
+    pub(crate) fn entry(&self, key: &str) -> Option<Option<i32>> {
+        if self.banned.contains_key(key) {
+            return Some(None);
+        }
+        self.words.get(key).map(|&(_, count)| Some(count))
+    }
+
+    /// The normalized words added by the layer within `distance` of the word `dfa` was
+    /// built for, with their distance.
+    pub(crate) fn search<'a>(
+        &'a self,
+        dfa: &DFA,
+        distance: u8,
+    ) -> impl Iterator<Item = (&'a str, u8)> + 'a {
+        self.index
+            .search(dfa)
+            .into_iter()
+            .filter(move |&(_, d)| d <= distance)
+            .map(|(id, d)| (self.keys[id as usize].as_str(), d))
+    }
+
+    /// The spelling `key` was added with, when it differs from the normalized form.
+    pub(crate) fn cased(&self, key: &str) -> Option<&str> {
+        self.words
+            .get(key)
+            .map(|(word, _)| word.as_str())
+            .filter(|word| *word != key)
+    }
+
+    pub(crate) fn longest_word(&self) -> usize {
+        self.longest_word
+    }
+
+    /// Normalize the keys for a speller with the given case sensitivity.
+    pub(crate) fn set_case_sensitive(&mut self, case_sensitive: bool) {
+        if self.case_sensitive == case_sensitive {
+            return;
+        }
+        let words = mem::take(&mut self.words);
+        let banned = mem::take(&mut self.banned);
+        *self = UserDictionary::new(case_sensitive);
+        for (word, count) in words.into_values() {
+            self.add_word(&word, count);
+        }
+        for word in banned.into_values() {
+            self.ban_word(&word);
+        }
+    }
+
+    fn normalize(&self, word: &str) -> String {
+        if self.case_sensitive {
+            word.to_string()
+        } else {
+            word.to_lowercase()
+        }
+    }
+}
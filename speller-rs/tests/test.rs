@@ -1,3 +1,41 @@
+use speller_rs::{Backend, Speller, SpellerBuilder};
+use std::collections::HashMap;
+
+/// Words shared by the tests that only need a few entries to correct against.
+const HELLO: &[(&str, i32)] = &[("hello", 10), ("help", 5), ("world", 8)];
+
+/// A dictionary source of words with their counts.
+fn words(counts: &[(&str, i32)]) -> HashMap<String, i32> {
+    counts
+        .iter()
+        .map(|&(word, count)| (word.to_string(), count))
+        .collect()
+}
+
+/// A builder loaded with nothing but words with their counts.
+fn builder_from(counts: &[(&str, i32)]) -> SpellerBuilder {
+    let mut builder = Speller::builder();
+    builder.dict_source(vec![words(counts)]);
+    builder
+}
+
+/// A speller built from nothing but words with their counts.
+fn speller_from(counts: &[(&str, i32)]) -> Speller {
+    builder_from(counts).build().unwrap()
+}
+
+/// A builder for the English test dictionary and n-grams, as saved to images.
+fn en_builder(backend: Backend) -> SpellerBuilder {
+    let mut builder = Speller::builder();
+    builder
+        .dict_file(vec!["./tests/files/en.txt".to_string()])
+        .ngram_file(vec!["./tests/files/ngrams.tsv".to_string()])
+        .distance(1)
+        .backend(backend)
+        .restore_case(true);
+    builder
+}
+
 #[cfg(test)]
 #[cfg(feature = "serde_json")]
 #[cfg(feature = "csv")]
@@ -128,3 +166,1956 @@ mod test_speller {
         assert!(speller.is_err());
     }
 }
+
+#[cfg(test)]
+mod test_lookup {
+    use super::speller_from;
+
+    const WORDS: &[(&str, i32)] = &[
+        ("hello", 10),
+        ("help", 5),
+        ("hero", 10),
+        ("world", 8),
+        ("word", 3),
+    ];
+
+    #[test]
+    fn test_candidates() {
+        let speller = speller_from(WORDS);
+        let candidates = speller.candidates("helo", 2).unwrap();
+        assert_eq!(candidates[0], Vec::<String>::new());
+        assert_eq!(candidates[1], vec!["help", "hello", "hero"]);
+        assert_eq!(candidates[2], Vec::<String>::new());
+        assert_eq!(speller.candidates("xyzzyq", 2), None);
+    }
+
+    #[test]
+    fn test_correction() {
+        let speller = speller_from(WORDS);
+        assert_eq!(speller.correction("wrold"), Some("world".to_string()));
+        assert_eq!(speller.correction("Hello"), Some("Hello".to_string()));
+        assert_eq!(speller.correction("xyzzyq"), None);
+    }
+
+    #[test]
+    fn test_correction_ranking() {
+        let speller = speller_from(WORDS);
+        // "hello" and "hero" are both at distance 1 with the same count, "help" is rarer.
+        let suggestion = speller.correction_with_score("helo").unwrap();
+        assert_eq!(suggestion.word, "hello");
+        assert_eq!(suggestion.distance, 1);
+        assert_eq!(suggestion.count, 10);
+
+        let suggestion = speller.correction_with_score("help").unwrap();
+        assert_eq!((suggestion.distance, suggestion.count), (0, 5));
+    }
+
+    #[test]
+    fn test_suggest() {
+        let speller = speller_from(WORDS);
+        let suggestions = speller.suggest("helo", 10);
+        let words: Vec<&str> = suggestions.iter().map(|s| s.word.as_str()).collect();
+        assert_eq!(words, vec!["hello", "hero", "help"]);
+        assert!(suggestions.windows(2).all(|w| w[0].score >= w[1].score));
+        assert_eq!((suggestions[2].distance, suggestions[2].count), (1, 5));
+
+        assert_eq!(speller.suggest("helo", 2).len(), 2);
+        assert_eq!(speller.suggest("hello", 1)[0].distance, 0);
+        assert!(speller.suggest("xyzzyq", 5).is_empty());
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test_backend {
+    use speller_rs::{Backend, Speller};
+
+    #[test]
+    fn test_symmetric_delete_matches_automaton() {
+        let build = |backend| {
+            Speller::builder()
+                .dict_file(vec!["./tests/files/en.json".to_string()])
+                .distance(1)
+                .backend(backend)
+                .build()
+                .unwrap()
+        };
+        let automaton = build(Backend::Automaton);
+        let symspell = build(Backend::SymmetricDelete);
+        let words = [
+            "helo", "wrold", "speling", "teh", "Yessss", "obrigada", "haa",
+        ];
+        for word in words.iter() {
+            assert_eq!(automaton.correction(word), symspell.correction(word));
+            assert_eq!(automaton.candidates(word, 1), symspell.candidates(word, 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_error_model {
+    use super::{speller_from, words};
+    use speller_rs::error::BuildError;
+    use speller_rs::error_model::ErrorModel;
+    use speller_rs::Speller;
+
+    const PAIRS: &[u8] = b"teh the\nrecieve receive\nadn and\n\nwierd weird\nthier their\n";
+
+    const WORDS: &[(&str, i32)] = &[("the", 100), ("hate", 500)];
+
+    #[test]
+    fn test_learn() {
+        let model = ErrorModel::from_reader(PAIRS).unwrap();
+        let transposition = model.log_probability("teh", "the");
+        let deletion = model.log_probability("hte", "hate");
+        assert!(transposition > deletion);
+        assert_eq!(model.log_probability("the", "the"), 0.0);
+        assert!(matches!(
+            ErrorModel::from_reader(&b"teh the\n\nlonely\n"[..]),
+            Err(BuildError::InvalidErrorModel(3))
+        ));
+    }
+
+    #[test]
+    fn test_ranking() {
+        let plain = speller_from(WORDS);
+        assert_eq!(plain.correction("hte"), Some("hate".to_string()));
+
+        let model = ErrorModel::from_reader(PAIRS).unwrap();
+        let speller = Speller::builder()
+            .dict_source(vec![words(WORDS)])
+            .error_model(model)
+            .build()
+            .unwrap();
+        assert_eq!(speller.correction("hte"), Some("the".to_string()));
+        assert_eq!(speller.suggest("hte", 2)[0].word, "the");
+    }
+}
+
+#[cfg(test)]
+mod test_keyboard {
+    use super::{speller_from, words};
+    use speller_rs::keyboard::Keyboard;
+    use speller_rs::{edit_distance, keyboard_edit_distance, Speller};
+
+    #[test]
+    fn test_adjacency() {
+        let qwerty = Keyboard::qwerty();
+        assert!(qwerty.is_adjacent('a', 's'));
+        assert!(qwerty.is_adjacent('S', 'z'));
+        assert!(!qwerty.is_adjacent('a', 'l'));
+        assert!(!qwerty.is_adjacent('a', 'a'));
+        assert!(Keyboard::azerty().is_adjacent('a', 'z'));
+        assert!(Keyboard::qwertz().is_adjacent('t', 'z'));
+        assert!(Keyboard::dvorak().is_adjacent('a', 'o'));
+
+        let custom = Keyboard::from_reader(&b"# two rows\nabc\n\ndef\n"[..]).unwrap();
+        assert!(custom.is_adjacent('a', 'd'));
+        assert!(!custom.is_adjacent('a', 'f'));
+    }
+
+    #[test]
+    fn test_keyboard_edit_distance() {
+        let qwerty = Keyboard::qwerty();
+        assert_eq!(keyboard_edit_distance("teat", "test", &qwerty), 0.5);
+        assert_eq!(keyboard_edit_distance("teat", "team", &qwerty), 1.0);
+        assert_eq!(
+            keyboard_edit_distance("kitten", "sitting", &qwerty),
+            edit_distance("kitten", "sitting", 10).unwrap() as f64
+        );
+    }
+
+    #[test]
+    fn test_correction() {
+        let counts = [("test", 10), ("teal", 10), ("team", 20), ("heat", 20)];
+        let plain = speller_from(&counts);
+        assert_eq!(plain.correction("teat"), Some("heat".to_string()));
+
+        let speller = Speller::builder()
+            .dict_source(vec![words(&counts)])
+            .keyboard(Keyboard::qwerty())
+            .build()
+            .unwrap();
+        assert_eq!(speller.correction("teat"), Some("test".to_string()));
+        assert_eq!(speller.suggest("teat", 1)[0].word, "test");
+    }
+}
+
+#[cfg(test)]
+mod test_check_text {
+    use super::speller_from;
+    use speller_rs::tokenize::{tokenize, TokenKind};
+
+    #[test]
+    fn test_tokenize() {
+        let text = "Don't re-read https://example.com/a?b=1, mail me@example.org (3.14) ok.";
+        let tokens: Vec<(&str, TokenKind)> = tokenize(text)
+            .iter()
+            .map(|token| (token.text, token.kind))
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                ("Don't", TokenKind::Word),
+                ("re-read", TokenKind::Word),
+                ("https://example.com/a?b=1", TokenKind::Url),
+                ("mail", TokenKind::Word),
+                ("me@example.org", TokenKind::Email),
+                ("3.14", TokenKind::Number),
+                ("ok", TokenKind::Word),
+            ]
+        );
+
+        let tokens = tokenize("né café");
+        assert_eq!(tokens[1].text, "café");
+        assert_eq!(tokens[1].byte_span, 4..9);
+        assert_eq!(tokens[1].char_span, 3..7);
+    }
+
+    #[test]
+    fn test_check_text() {
+        let speller = speller_from(&[
+            ("the", 100),
+            ("cat", 10),
+            ("sat", 10),
+            ("on", 50),
+            ("well", 5),
+            ("known", 5),
+            ("café", 5),
+        ]);
+
+        let text = "Thé cat sta on the well-knwn café, see www.example.com 42 times.";
+        let misspellings = speller.check_text(text);
+        let words: Vec<&str> = misspellings.iter().map(|m| m.word.as_str()).collect();
+        assert_eq!(words, vec!["Thé", "sta", "knwn", "see", "times"]);
+
+        let thé = &misspellings[0];
+        assert_eq!((thé.byte_span.clone(), thé.char_span.clone()), (0..4, 0..3));
+        assert_eq!(thé.suggestions[0], "the");
+
+        let knwn = &misspellings[2];
+        assert_eq!(&text[knwn.byte_span.clone()], "knwn");
+        assert_eq!(knwn.char_span, 24..28);
+        assert_eq!(knwn.suggestions, vec!["known"]);
+    }
+
+    #[test]
+    fn test_find_misspellings() {
+        let speller = speller_from(&[("the", 100), ("cat", 10), ("known", 5)]);
+        let text = "Teh cat, well-knwn.";
+        let found = speller.find_misspellings(text);
+        let checked = speller.check_text(text);
+        assert_eq!(found.len(), checked.len());
+        for (found, checked) in found.iter().zip(&checked) {
+            assert_eq!(
+                (&found.word, &found.byte_span, &found.char_span),
+                (&checked.word, &checked.byte_span, &checked.char_span)
+            );
+            assert!(found.suggestions.is_empty());
+        }
+        assert!(!checked[0].suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_correct_text() {
+        let speller = speller_from(&[("the", 100), ("cat", 10), ("sat", 10), ("known", 5)]);
+
+        assert_eq!(
+            speller.correct_text("Teh  cta SAAT,\n\tthe well-knwn xyzzyq!"),
+            "The  cat SAT,\n\tthe well-known xyzzyq!"
+        );
+        assert_eq!(speller.correct_text(""), "");
+    }
+}
+
+#[cfg(test)]
+mod test_case {
+    use super::builder_from;
+
+    const WORDS: &[(&str, i32)] = &[
+        ("hello", 100),
+        ("iphone", 10),
+        ("London", 20),
+        ("Paris", 20),
+        ("paris", 5),
+    ];
+
+    #[test]
+    fn test_restore_case() {
+        let speller = builder_from(WORDS).restore_case(true).build().unwrap();
+        assert_eq!(speller.correction("helo"), Some("hello".to_string()));
+        assert_eq!(speller.correction("Helo"), Some("Hello".to_string()));
+        assert_eq!(speller.correction("HELO"), Some("HELLO".to_string()));
+        assert_eq!(speller.correction("iPhome"), Some("iPhone".to_string()));
+        assert_eq!(speller.correction("londn"), Some("London".to_string()));
+        assert_eq!(speller.correction("london"), Some("London".to_string()));
+        assert_eq!(speller.correction("LONDN"), Some("LONDON".to_string()));
+        // "paris" is also a common noun in this dictionary.
+        assert_eq!(speller.correction("pariss"), Some("paris".to_string()));
+        assert_eq!(speller.suggest("Helo", 1)[0].word, "Hello");
+    }
+
+    #[test]
+    fn test_without_restore_case() {
+        let speller = builder_from(WORDS).restore_case(false).build().unwrap();
+        assert_eq!(speller.correction("Helo"), Some("hello".to_string()));
+        assert_eq!(speller.correction("London"), Some("London".to_string()));
+        assert_eq!(speller.correct_text("Helo from londn"), "Hello from London");
+    }
+}
+
+#[cfg(test)]
+mod test_ngram {
+    use super::builder_from;
+    use speller_rs::error::BuildError;
+    use speller_rs::Speller;
+
+    #[test]
+    fn test_real_word_errors() {
+        let speller = builder_from(&[
+            ("i", 500),
+            ("got", 100),
+            ("a", 1000),
+            ("letter", 50),
+            ("form", 100),
+            ("from", 500),
+            ("him", 200),
+            ("their", 300),
+            ("there", 400),
+            ("the", 2000),
+            ("car", 100),
+            ("is", 800),
+            ("over", 200),
+            ("fill", 50),
+            ("in", 900),
+        ])
+        .ngram_file(vec!["./tests/files/ngrams.tsv".to_string()])
+        .build()
+        .unwrap();
+        assert_eq!(
+            speller.correct_sentence("I got a letter form him."),
+            "I got a letter from him."
+        );
+        assert_eq!(
+            speller.correct_sentence("The car is over their!"),
+            "The car is over there!"
+        );
+        assert_eq!(
+            speller.correct_sentence("Fill in the form. Their car"),
+            "Fill in the form. Their car"
+        );
+        assert_eq!(
+            speller.correct_sentence("a lettr from him"),
+            "a letter from him"
+        );
+    }
+
+    #[test]
+    fn test_bad_ngram_file() {
+        let speller = Speller::builder()
+            .dict_file(vec!["./tests/files/en.txt".to_string()])
+            .ngram_file(vec!["./tests/files/en.txt".to_string()])
+            .build();
+        assert!(speller.is_err());
+    }
+
+    #[test]
+    fn test_non_positive_count() {
+        let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("zero_ngrams.tsv");
+        std::fs::write(&path, "the\tcar\t5\nthe\tform\t0\n").unwrap();
+        let speller = Speller::builder()
+            .dict_file(vec!["./tests/files/en.txt".to_string()])
+            .ngram_file(vec![path.to_str().unwrap().to_string()])
+            .build();
+        assert!(matches!(speller, Err(BuildError::ParseCountError)));
+    }
+}
+
+#[cfg(test)]
+mod test_mutation {
+    use super::{builder_from, words, HELLO};
+    use speller_rs::{Backend, Speller};
+
+    #[test]
+    fn test_add_remove_set_count() {
+        for backend in [Backend::Automaton, Backend::SymmetricDelete] {
+            let mut speller = builder_from(HELLO).backend(backend).build().unwrap();
+            assert_eq!(speller.correction("kubernets"), None);
+
+            speller.add_word("Kubernetes", 3);
+            assert_eq!(
+                speller.correction("kubernets"),
+                Some("kubernetes".to_string())
+            );
+            assert!(speller.check_text("Kubernetes").is_empty());
+
+            assert!(speller.remove_word("hello"));
+            assert!(!speller.remove_word("hello"));
+            assert_eq!(speller.correction("helo"), Some("help".to_string()));
+            // "world" was moved in place of "hello" and must still be found.
+            assert_eq!(speller.correction("wrold"), Some("world".to_string()));
+            assert_eq!(
+                speller.correction("kubernetez"),
+                Some("kubernetes".to_string())
+            );
+
+            speller.add_word("hero", 1);
+            assert_eq!(speller.correction("helo"), Some("help".to_string()));
+            speller.set_count("hero", 50);
+            assert_eq!(speller.correction("helo"), Some("hero".to_string()));
+            speller.add_word("help", 100);
+            let suggestion = speller.correction_with_score("helo").unwrap();
+            assert_eq!((suggestion.word.as_str(), suggestion.count), ("help", 105));
+            speller.add_word("help", i32::MAX);
+            let suggestion = speller.correction_with_score("helo").unwrap();
+            assert_eq!(suggestion.count, i32::MAX);
+
+            assert!(speller.remove_word("kubernetes"));
+            assert_eq!(speller.correction("kubernets"), None);
+        }
+    }
+
+    #[test]
+    fn test_count_overflow() {
+        // Counts of a word listed in several files are added up without overflowing.
+        let mut speller = Speller::builder()
+            .dict_source(vec![
+                words(&[("help", i32::MAX)]),
+                words(&[("help", i32::MAX)]),
+            ])
+            .build()
+            .unwrap();
+        assert_eq!(
+            speller.correction_with_score("helo").unwrap().count,
+            i32::MAX
+        );
+
+        speller.add_word("hero", i32::MAX);
+        speller.add_word("hero", i32::MAX);
+        speller.remove_word("help");
+        assert_eq!(
+            speller.correction_with_score("herp").unwrap().count,
+            i32::MAX
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_layers {
+    use super::{builder_from, HELLO};
+    use speller_rs::user::UserDictionary;
+
+    #[test]
+    fn test_add_ban_override() {
+        let base = builder_from(HELLO).restore_case(true).build().unwrap();
+        let mut tenant = base.clone();
+        let mut layer = UserDictionary::new(false);
+        layer.add_word("Kubernetes", 3);
+        layer.add_word("help", 50);
+        layer.ban_word("world");
+        tenant.push_layer(layer);
+
+        assert!(tenant.check_text("Kubernetes").is_empty());
+        assert_eq!(
+            tenant.correction("kubernets"),
+            Some("Kubernetes".to_string())
+        );
+        assert_eq!(tenant.correction("helo"), Some("help".to_string()));
+        assert_eq!(tenant.check_text("world").len(), 1);
+        assert_eq!(tenant.correction("wrold"), None);
+
+        // The base speller is not affected by the tenant's layer.
+        assert_eq!(base.correction("kubernets"), None);
+        assert_eq!(base.correction("helo"), Some("hello".to_string()));
+        assert_eq!(base.correction("wrold"), Some("world".to_string()));
+    }
+
+    #[test]
+    fn test_layer_order() {
+        let mut speller = builder_from(HELLO).restore_case(true).build().unwrap();
+        let mut bottom = UserDictionary::new(false);
+        bottom.ban_word("hello");
+        let mut top = UserDictionary::new(false);
+        top.add_word("hello", 1);
+        speller.push_layer(bottom);
+        speller.push_layer(top);
+        assert!(speller.check_text("hello").is_empty());
+
+        speller.layer_mut(1).unwrap().remove("hello");
+        assert_eq!(speller.check_text("hello").len(), 1);
+        assert_eq!(speller.pop_layer().unwrap().count("hello"), None);
+        assert_eq!(speller.layers().len(), 1);
+    }
+
+    #[test]
+    fn test_layer_lookup() {
+        let mut speller = builder_from(HELLO).restore_case(true).build().unwrap();
+        let mut layer = UserDictionary::new(false);
+        for i in 0..1000 {
+            layer.add_word(&format!("term{i}"), 1);
+        }
+        layer.add_word("kubectl", 1);
+        speller.push_layer(layer);
+        assert_eq!(speller.correction("kubectel"), Some("kubectl".to_string()));
+
+        // Removed words are no longer suggested, and come back when added again.
+        let layer = speller.layer_mut(0).unwrap();
+        layer.remove("kubectl");
+        assert_eq!(speller.correction("kubectel"), None);
+        speller.layer_mut(0).unwrap().add_word("kubectl", 1);
+        assert_eq!(speller.correction("kubectel"), Some("kubectl".to_string()));
+    }
+
+    #[test]
+    fn test_save_load() {
+        let mut layer = UserDictionary::new(false);
+        layer.add_word("Kubernetes", 3);
+        layer.add_word("kubectl", 1);
+        layer.ban_word("teh");
+        let mut saved = vec![];
+        layer.write_to(&mut saved).unwrap();
+        assert_eq!(
+            String::from_utf8(saved.clone()).unwrap(),
+            "Kubernetes 3\nkubectl 1\n!teh\n"
+        );
+        let loaded = UserDictionary::from_reader(&saved[..], false).unwrap();
+        assert_eq!(loaded, layer);
+
+        let layer = UserDictionary::from_reader("# jargon\n\nsharded\n".as_bytes(), false).unwrap();
+        assert_eq!(layer.count("Sharded"), Some(1));
+    }
+}
+
+#[cfg(test)]
+mod test_image {
+    use super::en_builder;
+    use speller_rs::error::BuildError;
+    use speller_rs::error_model::ErrorModel;
+    use speller_rs::keyboard::Keyboard;
+    use speller_rs::{Backend, Speller};
+
+    #[test]
+    fn test_save_load() {
+        for backend in [Backend::Automaton, Backend::SymmetricDelete] {
+            let mut speller = en_builder(backend)
+                .error_model(ErrorModel::new().with_transposition(0.01))
+                .keyboard(Keyboard::qwerty())
+                .build()
+                .unwrap();
+            speller.add_word("kubernetes", 3);
+            speller.remove_word("hello");
+            let path = std::env::temp_dir().join(format!("speller-{backend:?}.bin"));
+            speller.save(&path).unwrap();
+            let loaded = Speller::load(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            let words = ["Helo", "wrold", "speling", "teh", "KUBERNETS", "haa"];
+            for word in words.iter() {
+                assert_eq!(speller.correction(word), loaded.correction(word));
+                assert_eq!(speller.suggest(word, 5), loaded.suggest(word, 5));
+                assert_eq!(speller.candidates(word, 1), loaded.candidates(word, 1));
+            }
+            let text = "Teh letter form him";
+            assert_eq!(speller.check_text(text), loaded.check_text(text));
+            assert_eq!(
+                speller.correct_sentence(text),
+                loaded.correct_sentence(text)
+            );
+        }
+    }
+
+    #[test]
+    fn test_corrupted_image() {
+        let mut image = vec![];
+        en_builder(Backend::Automaton)
+            .build()
+            .unwrap()
+            .write_to(&mut image)
+            .unwrap();
+
+        let mut corrupted = image.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 1;
+        assert!(matches!(
+            Speller::from_bytes(&corrupted),
+            Err(BuildError::ChecksumMismatch)
+        ));
+
+        let mut newer = image.clone();
+        newer[8] = 99;
+        assert!(matches!(
+            Speller::from_bytes(&newer),
+            Err(BuildError::UnsupportedImageVersion(99))
+        ));
+
+        assert!(matches!(
+            Speller::from_bytes(&image[..image.len() / 2]),
+            Err(BuildError::ChecksumMismatch)
+        ));
+        assert!(matches!(
+            Speller::from_bytes(b"{\"hello\": 1}"),
+            Err(BuildError::InvalidImage)
+        ));
+    }
+}
+
+#[cfg(test)]
+mod test_mapped {
+    use super::en_builder;
+    use speller_rs::user::UserDictionary;
+    use speller_rs::{Backend, Speller};
+
+    #[test]
+    fn test_load_mapped() {
+        for backend in [Backend::Automaton, Backend::SymmetricDelete] {
+            let speller = en_builder(backend).build().unwrap();
+            let path = std::env::temp_dir().join(format!("speller-mapped-{backend:?}.bin"));
+            speller.save(&path).unwrap();
+            let mapped = Speller::load_mapped(&path).unwrap();
+
+            let words = ["Helo", "wrold", "speling", "teh", "hello", "haa"];
+            for word in words.iter() {
+                assert_eq!(
+                    speller.correction_with_score(word),
+                    mapped.correction_with_score(word)
+                );
+                assert_eq!(speller.correction(word), mapped.correction(word));
+                assert_eq!(speller.suggest(word, 5), mapped.suggest(word, 5));
+                assert_eq!(speller.candidates(word, 1), mapped.candidates(word, 1));
+            }
+            let text = "Teh letter form him";
+            assert_eq!(speller.check_text(text), mapped.check_text(text));
+            assert_eq!(
+                speller.correct_sentence(text),
+                mapped.correct_sentence(text)
+            );
+
+            // Saving a mapped speller writes the same image back.
+            let mut image = vec![];
+            mapped.write_to(&mut image).unwrap();
+            assert_eq!(image, std::fs::read(&path).unwrap());
+            drop(mapped);
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_mapped_mutation() {
+        let path = std::env::temp_dir().join("speller-mapped-mutation.bin");
+        en_builder(Backend::Automaton)
+            .build()
+            .unwrap()
+            .save(&path)
+            .unwrap();
+        let base = Speller::load_mapped(&path).unwrap();
+
+        let mut tenant = base.clone();
+        let mut layer = UserDictionary::new(false);
+        layer.add_word("kubernetes", 3);
+        tenant.push_layer(layer);
+        assert!(tenant.check_text("kubernetes").is_empty());
+
+        let mut changed = base.clone();
+        changed.add_word("kubectl", 2);
+        assert!(changed.remove_word("hello"));
+        assert!(changed.check_text("kubectl").is_empty());
+        assert!(!changed.check_text("hello").is_empty());
+        assert!(changed.check_text("world").is_empty());
+
+        assert!(!base.check_text("kubernetes").is_empty());
+        assert!(!base.check_text("kubectl").is_empty());
+        assert!(base.check_text("hello").is_empty());
+        drop((base, tenant, changed));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test_hunspell {
+    use speller_rs::error::BuildError;
+    use speller_rs::hunspell::Affixes;
+    use speller_rs::source::{HunspellWordLoader, WordLoader};
+    use speller_rs::Speller;
+    use std::fs::File;
+
+    fn load(name: &str) -> Vec<String> {
+        let aff = format!("./tests/files/{name}.aff");
+        let mut loader = HunspellWordLoader::from_aff_file(aff).unwrap();
+        let dic = File::open(format!("./tests/files/{name}.dic")).unwrap();
+        let mut words: Vec<String> = loader.load_words(dic).unwrap().into_keys().collect();
+        words.sort();
+        words
+    }
+
+    #[test]
+    fn test_expansion() {
+        let words = load("en_hunspell");
+        let expected = [
+            "box",
+            "boxes",
+            "colours",
+            "happiness",
+            "happinesses",
+            "happy",
+            "kind",
+            "kindly",
+            "kindness",
+            "kindnesses",
+            "quick",
+            "quickly",
+            "rework",
+            "reworked",
+            "reworks",
+            "tried",
+            "tries",
+            "try",
+            "unhappy",
+            "unkind",
+            "work",
+            "worked",
+            "works",
+        ];
+        assert_eq!(words, expected);
+    }
+
+    #[test]
+    fn test_long_flags_and_aliases() {
+        assert_eq!(
+            load("long_flags"),
+            ["café", "café'", "cafés", "cafés'", "hero", "hero'"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_affix_file() {
+        let aff = "SET KOI8-R\n".as_bytes();
+        assert!(matches!(
+            Affixes::from_reader(aff),
+            Err(BuildError::UnsupportedEncoding(encoding)) if encoding == "KOI8-R"
+        ));
+        let aff = "SFX S Y 1\nSFX S 0 s [ab\n".as_bytes();
+        assert!(matches!(
+            Affixes::from_reader(aff),
+            Err(BuildError::InvalidAffixFile)
+        ));
+    }
+
+    #[test]
+    fn test_speller_hunspell() {
+        let speller = Speller::builder()
+            .dict_file(vec!["./tests/files/en_hunspell.dic".to_string()])
+            .build()
+            .unwrap();
+        let misspelled: Vec<String> = speller
+            .check_text("Kindness, unhappy boxes, reworkd colours")
+            .into_iter()
+            .map(|misspelling| misspelling.word)
+            .collect();
+        assert_eq!(misspelled, ["reworkd"]);
+        assert_eq!(speller.correction("kindnes"), Some("kindness".to_string()));
+        assert_eq!(speller.correction("irregardless"), None);
+    }
+}
+
+#[cfg(test)]
+mod test_morphology {
+    use speller_rs::hunspell::Affixes;
+    use speller_rs::morphology::Morphology;
+    use speller_rs::source::{HunspellWordLoader, WordLoader};
+    use speller_rs::Speller;
+    use std::fs::File;
+
+    fn misspelled(speller: &Speller, text: &str) -> Vec<String> {
+        speller
+            .check_text(text)
+            .into_iter()
+            .map(|misspelling| misspelling.word)
+            .collect()
+    }
+
+    #[test]
+    fn test_stems() {
+        let affixes = Affixes::from_file("./tests/files/lv_hunspell.aff").unwrap();
+        let dic = File::open("./tests/files/lv_hunspell.dic").unwrap();
+        let morphology = Morphology::from_reader(affixes, dic, false).unwrap();
+        assert_eq!(morphology.stems("mājās"), ["māja"]);
+        assert_eq!(morphology.stems("neredzam"), ["redzēt"]);
+        assert_eq!(morphology.stems("lasīt"), ["lasīt"]);
+        assert!(morphology.stems("lasu").is_empty());
+        assert!(morphology.stems("mājam").is_empty());
+    }
+
+    #[test]
+    fn test_known_forms() {
+        let speller = Speller::builder()
+            .dict_file(vec!["./tests/files/lv_hunspell.dic".to_string()])
+            .morphology(true)
+            .build()
+            .unwrap();
+        assert!(misspelled(&speller, "Mājās, skolai, grāmatu, neredzi, redzam").is_empty());
+        assert_eq!(misspelled(&speller, "skolam, lasu"), ["skolam", "lasu"]);
+    }
+
+    #[test]
+    fn test_keep_inflection() {
+        let speller = Speller::builder()
+            .dict_file(vec!["./tests/files/lv_hunspell.dic".to_string()])
+            .morphology(true)
+            .build()
+            .unwrap();
+        assert_eq!(speller.correction("mjās"), Some("mājās".to_string()));
+        assert_eq!(speller.correction("grmatai"), Some("grāmatai".to_string()));
+        assert_eq!(speller.correction("neredzm"), Some("neredzam".to_string()));
+    }
+
+    #[test]
+    fn test_same_words_as_expansion() {
+        let mut loader =
+            HunspellWordLoader::from_aff_file("./tests/files/en_hunspell.aff").unwrap();
+        let dic = File::open("./tests/files/en_hunspell.dic").unwrap();
+        let expanded = loader.load_words(dic).unwrap();
+        let speller = Speller::builder()
+            .dict_file(vec!["./tests/files/en_hunspell.dic".to_string()])
+            .morphology(true)
+            .build()
+            .unwrap();
+        for word in expanded.keys() {
+            assert!(misspelled(&speller, word).is_empty(), "{word}");
+        }
+        assert_eq!(
+            misspelled(&speller, "colour irregardless unkindly"),
+            ["colour", "irregardless", "unkindly"]
+        );
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test_streaming {
+    use speller_rs::source::{
+        Cutoff, Format, JsonWordLoader, StreamingWordLoader, TextWordLoader, WordLoader,
+    };
+    use speller_rs::Speller;
+    use std::collections::HashMap;
+    use std::fs::File;
+    use std::io::BufReader;
+
+    fn stream<W: StreamingWordLoader>(loader: &mut W, path: &str) -> HashMap<String, i32> {
+        let mut words = HashMap::new();
+        let reader = BufReader::new(File::open(path).unwrap());
+        loader
+            .stream_words(reader, |word, count| {
+                words.insert(word, count);
+            })
+            .unwrap();
+        words
+    }
+
+    #[test]
+    fn test_stream_words() {
+        let reader = BufReader::new(File::open("./tests/files/en.json").unwrap());
+        let loaded = JsonWordLoader::new().load_words(reader).unwrap();
+        assert_eq!(
+            stream(&mut JsonWordLoader::new(), "./tests/files/en.json"),
+            loaded
+        );
+        assert_eq!(
+            stream(&mut TextWordLoader::new(), "./tests/files/en.txt"),
+            loaded
+        );
+    }
+
+    #[test]
+    fn test_cutoff() {
+        let cutoff = Cutoff::new().with_min_count(100).with_top_n(10);
+        assert_eq!(cutoff.min_count, Some(100));
+        assert_eq!(cutoff.top_n, Some(10));
+
+        let dict = |speller: &Speller, word: &str| speller.check_text(word).is_empty();
+        let speller = Speller::builder()
+            .dict_file(vec!["./tests/files/en.json".to_string()])
+            .min_count(100)
+            .build()
+            .unwrap();
+        // "aardvark" has a count of 106 and "aardvarks" of 50.
+        assert!(dict(&speller, "aardvark"));
+        assert!(!dict(&speller, "aardvarks"));
+
+        let speller = Speller::builder()
+            .dict_file(vec!["./tests/files/en.txt".to_string()])
+            .top_n(3)
+            .build()
+            .unwrap();
+        // The most frequent words are "the", "you", "i" and "to".
+        for word in ["the", "you", "i"] {
+            assert!(dict(&speller, word));
+        }
+        assert!(!dict(&speller, "to"));
+        assert!(!dict(&speller, "and"));
+    }
+
+    #[test]
+    fn test_duplicate_words() {
+        let known = |text: &'static str, cutoff: Cutoff, word: &str| {
+            let mut builder = Speller::builder();
+            builder.dict_bytes(text.as_bytes(), Format::Text);
+            if let Some(min_count) = cutoff.min_count {
+                builder.min_count(min_count);
+            }
+            if let Some(top_n) = cutoff.top_n {
+                builder.top_n(top_n);
+            }
+            builder.build().unwrap().check_text(word).is_empty()
+        };
+        // The last count of a word wins, as when a loader collects the file, and the cutoff
+        // applies to it rather than to each line.
+        let text = "rare 500\nrare 5\ncommon 50\n";
+        let loaded = TextWordLoader::new().load_words(text.as_bytes()).unwrap();
+        assert_eq!(loaded.get("rare"), Some(&5));
+        assert!(!known(text, Cutoff::new().with_min_count(10), "rare"));
+        assert!(known(text, Cutoff::new().with_min_count(10), "common"));
+
+        let text = "twice 20\ntwice 20\nonce 30\n";
+        assert!(known(text, Cutoff::new().with_top_n(1), "once"));
+        assert!(!known(text, Cutoff::new().with_top_n(1), "twice"));
+        assert!(known(text, Cutoff::new().with_top_n(2), "twice"));
+
+        let text = "big 90\nbig 1\nmid 50\n";
+        assert!(known(text, Cutoff::new().with_top_n(1), "mid"));
+        assert!(!known(text, Cutoff::new().with_top_n(1), "big"));
+        assert!(!known(text, Cutoff::new().with_min_count(10), "big"));
+    }
+
+    #[test]
+    fn test_stream_cased() {
+        let speller = Speller::builder()
+            .dict_file(vec!["./tests/files/en.json".to_string()])
+            .restore_case(true)
+            .build()
+            .unwrap();
+        let expected = Speller::builder()
+            .dict_source(vec![JsonWordLoader::new()
+                .load_words(File::open("./tests/files/en.json").unwrap())
+                .unwrap()])
+            .restore_case(true)
+            .build()
+            .unwrap();
+        for word in ["Londn", "englsh", "paris", "Amrica"] {
+            assert_eq!(speller.correction(word), expected.correction(word));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_compression {
+    use speller_rs::compression::{decompress, strip_extension, Compression};
+    use speller_rs::error::BuildError;
+    use speller_rs::Speller;
+    use std::io::{BufReader, Read};
+    use std::path::Path;
+
+    fn build(file: &str) -> Result<Speller, BuildError> {
+        Speller::builder()
+            .dict_file(vec![format!("./tests/files/{file}")])
+            .build()
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            Compression::detect(&[0x1f, 0x8b, 8]),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::detect(&[0x28, 0xb5, 0x2f, 0xfd]),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(Compression::detect(b"\xfd7zXZ\x00"), Some(Compression::Xz));
+        assert_eq!(Compression::detect(b"{\"hello\": 1}"), None);
+        assert_eq!(
+            Compression::from_path("en.json.zst"),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(
+            strip_extension("data/en.json.gz"),
+            Path::new("data/en.json")
+        );
+        assert_eq!(strip_extension("data/en.json"), Path::new("data/en.json"));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip() {
+        let speller = build("small.txt.gz").unwrap();
+        assert_eq!(speller.correction("helo"), Some("hello".to_string()));
+        // Detected by its magic bytes, whatever its name.
+        let speller = build("small_gzip.txt").unwrap();
+        assert_eq!(speller.correction("wrld"), Some("world".to_string()));
+    }
+
+    #[cfg(all(feature = "zstd", feature = "serde_json"))]
+    #[test]
+    fn test_zstd() {
+        let speller = build("small.json.zst").unwrap();
+        assert_eq!(speller.correction("pyton"), Some("python".to_string()));
+    }
+
+    #[cfg(all(feature = "xz", feature = "csv"))]
+    #[test]
+    fn test_xz() {
+        let speller = build("small.csv.xz").unwrap();
+        assert_eq!(speller.correction("helo"), Some("hello".to_string()));
+    }
+
+    /// A reader that hands over one byte at a time, like a slow pipe.
+    struct Trickle<R>(R);
+
+    impl<R: Read> Read for Trickle<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let length = buf.len().min(1);
+            self.0.read(&mut buf[..length])
+        }
+    }
+
+    #[test]
+    fn test_short_reads() {
+        let file = std::fs::read("./tests/files/small.csv.xz").unwrap();
+        let reader = decompress(BufReader::new(Trickle(&file[..])));
+        #[cfg(feature = "xz")]
+        {
+            let mut text = String::new();
+            reader.unwrap().read_to_string(&mut text).unwrap();
+            assert!(text.contains("hello"));
+        }
+        #[cfg(not(feature = "xz"))]
+        assert!(matches!(
+            reader,
+            Err(BuildError::CompressionNotEnabled("xz"))
+        ));
+
+        // Plain text shorter than any magic bytes is returned whole.
+        let mut text = String::new();
+        decompress(BufReader::new(Trickle(&b"hi 1"[..])))
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "hi 1");
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn test_compression_not_enabled() {
+        assert!(matches!(
+            build("small_gzip.txt"),
+            Err(BuildError::CompressionNotEnabled("gzip"))
+        ));
+    }
+}
+
+#[cfg(test)]
+mod test_input {
+    use speller_rs::error::BuildError;
+    use speller_rs::source::{Format, WordLoader};
+    use speller_rs::Speller;
+    use std::collections::HashMap;
+    use std::io;
+    use std::io::{BufRead, Cursor};
+
+    /// One word per line, each with a count of 10.
+    struct WordListLoader;
+
+    impl WordLoader for WordListLoader {
+        fn load_words<R: io::Read>(
+            &mut self,
+            reader: R,
+        ) -> Result<HashMap<String, i32>, BuildError> {
+            let mut words = HashMap::new();
+            for line in io::BufReader::new(reader).lines() {
+                words.insert(line?, 10);
+            }
+            Ok(words)
+        }
+    }
+
+    const WORDS: &[u8] = b"hello 1000\nworld 800\npython 500\n";
+
+    #[test]
+    fn test_dict_bytes() {
+        let speller = Speller::builder()
+            .dict_bytes(WORDS, Format::Text)
+            .build()
+            .unwrap();
+        assert_eq!(speller.correction("helo"), Some("hello".to_string()));
+
+        let speller = Speller::builder()
+            .dict_bytes(WORDS.to_vec(), Format::Text)
+            .build()
+            .unwrap();
+        assert_eq!(speller.correction("wrld"), Some("world".to_string()));
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_dict_bytes_json() {
+        let json = br#"{"hello": 1000, "world": 800}"#;
+        let speller = Speller::builder()
+            .dict_bytes(&json[..], Format::Json)
+            .build()
+            .unwrap();
+        assert_eq!(speller.correction("helo"), Some("hello".to_string()));
+    }
+
+    #[test]
+    fn test_dict_reader() {
+        let mut builder = Speller::builder();
+        builder.dict_reader(Cursor::new(WORDS.to_vec()), Format::Text);
+        let speller = builder.build().unwrap();
+        assert_eq!(speller.correction("pyton"), Some("python".to_string()));
+        assert!(matches!(builder.build(), Err(BuildError::ReaderConsumed)));
+    }
+
+    #[test]
+    fn test_register_loader() {
+        let speller = Speller::builder()
+            .register_loader("words", || WordListLoader)
+            .dict_file(vec!["./tests/files/small.words".to_string()])
+            .dict_bytes(&b"ansible\n"[..], Format::Extension("words".to_string()))
+            .build()
+            .unwrap();
+        assert_eq!(
+            speller.correction("kubernets"),
+            Some("kubernetes".to_string())
+        );
+        assert_eq!(speller.correction("ansble"), Some("ansible".to_string()));
+
+        assert!(matches!(
+            Speller::builder()
+                .dict_file(vec!["./tests/files/small.words".to_string()])
+                .build(),
+            Err(BuildError::FileTypeNotSupported)
+        ));
+    }
+}
+
+#[cfg(test)]
+mod test_word_list {
+    use speller_rs::error::BuildError;
+    use speller_rs::source::{Format, StreamingWordLoader, TextWordLoader, WordLoader};
+    use speller_rs::Speller;
+    use std::collections::HashMap;
+    use std::fs::File;
+
+    fn stream(loader: &mut TextWordLoader, text: &str) -> HashMap<String, i32> {
+        let mut words = HashMap::new();
+        loader
+            .stream_words(text.as_bytes(), |word, count| {
+                words.insert(word, count);
+            })
+            .unwrap();
+        words
+    }
+
+    #[test]
+    fn test_word_list_loader() {
+        let file = File::open("./tests/files/words").unwrap();
+        let words = TextWordLoader::word_list().load_words(file).unwrap();
+        assert_eq!(words.len(), 5);
+        assert_eq!(words.get("hello"), Some(&1));
+        assert_eq!(words.get("Zürich"), Some(&1));
+        assert!(!words.keys().any(|word| word.starts_with('#')));
+
+        // The whole line is the word: a space doesn't start a count column.
+        let mut loader = TextWordLoader::word_list().with_default_count(20);
+        let words = stream(&mut loader, "ad hoc\n  world\nhello 300\n");
+        assert_eq!(words.get("ad hoc"), Some(&20));
+        assert_eq!(words.get("world"), Some(&20));
+        assert_eq!(words.get("hello 300"), Some(&20));
+    }
+
+    #[test]
+    fn test_dedup() {
+        let text = "python 3\nrust\npython 7\n";
+        let words = TextWordLoader::new()
+            .with_default_count(1)
+            .load_words(text.as_bytes())
+            .unwrap();
+        assert_eq!(words.get("python"), Some(&7));
+
+        let mut loader = TextWordLoader::new().with_default_count(1).with_dedup(true);
+        let words = loader.load_words(text.as_bytes()).unwrap();
+        assert_eq!(words.get("python"), Some(&3));
+        assert_eq!(words.get("rust"), Some(&1));
+        assert_eq!(stream(&mut loader, text), words);
+    }
+
+    #[test]
+    fn test_missing_count() {
+        let mut loader = TextWordLoader::new();
+        assert!(matches!(
+            loader.load_words("hello 10\nworld\n".as_bytes()),
+            Err(BuildError::TXTIndexError)
+        ));
+        // Comments are only skipped in word lists.
+        assert!(matches!(
+            loader.load_words("# words\nhello 10\n".as_bytes()),
+            Err(BuildError::ParseCountError)
+        ));
+        let words = loader
+            .load_words("hello 10\n\nworld 5\n".as_bytes())
+            .unwrap();
+        assert_eq!(words.len(), 2);
+    }
+
+    #[test]
+    fn test_builder() {
+        let speller = Speller::builder()
+            .dict_file(vec!["./tests/files/words".to_string()])
+            .build()
+            .unwrap();
+        assert_eq!(speller.correction("pythn"), Some("python".to_string()));
+        assert_eq!(speller.correction("zurich"), Some("zürich".to_string()));
+
+        let speller = Speller::builder()
+            .dict_bytes(&b"kubectl\nhelm\n"[..], Format::WordList)
+            .build()
+            .unwrap();
+        assert_eq!(speller.correction("kubctl"), Some("kubectl".to_string()));
+
+        let speller = Speller::builder()
+            .dict_bytes(&b"kubectl\nhelm\n"[..], Format::WordList)
+            .word_list_count(40)
+            .build()
+            .unwrap();
+        assert_eq!(speller.suggest("kubctl", 1)[0].count, 40);
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn test_normalization() {
+        use speller_rs::source::Normalization;
+
+        let file = File::open("./tests/files/decomposed.lst").unwrap();
+        let words = TextWordLoader::word_list()
+            .with_normalization(Normalization::Nfc)
+            .load_words(file)
+            .unwrap();
+        assert_eq!(words.get("café"), Some(&1));
+        assert_eq!(words.get("naïve"), Some(&1));
+
+        let speller = Speller::builder()
+            .register_loader("lst", || {
+                TextWordLoader::word_list().with_normalization(Normalization::Nfc)
+            })
+            .dict_file(vec!["./tests/files/decomposed.lst".to_string()])
+            .build()
+            .unwrap();
+        assert_eq!(speller.correction("cafe"), Some("café".to_string()));
+    }
+}
+
+#[cfg(test)]
+mod test_code {
+    use super::speller_from;
+    use speller_rs::code::{prose, regions, split_identifier, Language, RegionKind};
+
+    /// The comments and strings of `text`.
+    fn texts(text: &str, language: Language) -> Vec<(RegionKind, &str)> {
+        regions(text, language)
+            .into_iter()
+            .filter(|region| region.kind != RegionKind::Identifier)
+            .map(|region| (region.kind, &text[region.byte_span]))
+            .collect()
+    }
+
+    /// The identifiers of `text`.
+    fn identifiers(text: &str, language: Language) -> Vec<&str> {
+        regions(text, language)
+            .into_iter()
+            .filter(|region| region.kind == RegionKind::Identifier)
+            .map(|region| &text[region.byte_span])
+            .collect()
+    }
+
+    #[test]
+    fn test_language() {
+        assert_eq!(Language::from_path("src/lib.rs"), Some(Language::Rust));
+        assert_eq!(Language::from_path("app.tsx"), Some(Language::TypeScript));
+        assert_eq!(Language::from_extension("mjs"), Some(Language::JavaScript));
+        assert_eq!(Language::from_path("README.md"), None);
+        assert_eq!(Language::from_path("Makefile"), None);
+    }
+
+    #[test]
+    fn test_rust_regions() {
+        let text = r####"/// Docs /* still */ a comment
+fn f<'a>(s: &'a str) -> char { /* outer /* inner */ outer */
+    let q = '"'; let e = '\''; let raw = r#"say "hi""#;
+    let b = b"bytes\" here"; g(q, "ok"); 'b'
+}"####;
+        assert_eq!(
+            texts(text, Language::Rust),
+            vec![
+                (RegionKind::Comment, "/ Docs /* still */ a comment"),
+                (RegionKind::Comment, " outer /* inner */ outer "),
+                (RegionKind::RawString, "say \"hi\""),
+                (RegionKind::String, "bytes\\\" here"),
+                (RegionKind::String, "ok"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_python_regions() {
+        let text =
+            "x = f'{a} value'  # comment's end\n\"\"\"Doc 'string'\nlines\"\"\"\nr'\\d+' if'x'";
+        assert_eq!(
+            texts(text, Language::Python),
+            vec![
+                (RegionKind::String, "{a} value"),
+                (RegionKind::Comment, " comment's end"),
+                (RegionKind::String, "Doc 'string'\nlines"),
+                (RegionKind::RawString, "\\d+"),
+                (RegionKind::String, "x"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_javascript_regions() {
+        let text = "const re = /[\"/]'/g; let x = a / b / c; // end\nf(`multi\nline`, 'it\\'s')";
+        assert_eq!(
+            texts(text, Language::JavaScript),
+            vec![
+                (RegionKind::Comment, " end"),
+                (RegionKind::String, "multi\nline"),
+                (RegionKind::String, "it\\'s"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_go_regions() {
+        let text = "r := '\"' // rune\ns := `raw \"text\"` + \"unterminated\nx";
+        assert_eq!(
+            texts(text, Language::Go),
+            vec![
+                (RegionKind::Comment, " rune"),
+                (RegionKind::RawString, "raw \"text\""),
+                (RegionKind::String, "unterminated"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_identifier_regions() {
+        let text = "fn f<'a>(s: &'a str) -> usize { let count = 0x1F; parse_http(s, \"x\") }";
+        assert_eq!(
+            identifiers(text, Language::Rust),
+            vec!["f", "s", "count", "parse_http", "s"]
+        );
+        let text = "def load(self, path):\n    return len(path)  # path";
+        assert_eq!(
+            identifiers(text, Language::Python),
+            vec!["load", "path", "path"]
+        );
+        let text = "const re = /a b/g; let total = count / 2;";
+        assert_eq!(
+            identifiers(text, Language::JavaScript),
+            vec!["re", "total", "count"]
+        );
+        let text = "interface Point { x: number; label: string }";
+        assert_eq!(
+            identifiers(text, Language::TypeScript),
+            vec!["Point", "x", "label"]
+        );
+        // TypeScript keywords are names in JavaScript.
+        assert_eq!(
+            identifiers("let interface;", Language::JavaScript),
+            vec!["interface"]
+        );
+        let text = "func main() { var n int64 = 10 }";
+        assert_eq!(identifiers(text, Language::Go), vec!["main", "n"]);
+    }
+
+    #[test]
+    fn test_prose() {
+        let string = |text| prose(text, RegionKind::String);
+        assert_eq!(string("%s items\\n%-8.2f left"), "   items         left");
+        assert_eq!(string("100% sure"), "100% sure");
+        assert_eq!(string("50% of users"), "50% of users");
+        assert_eq!(string("% done"), "% done");
+        assert_eq!(prose("\\n%s", RegionKind::RawString), "\\n%s");
+    }
+
+    #[test]
+    fn test_split_identifier() {
+        let split = |identifier: &'static str| -> Vec<&'static str> {
+            split_identifier(identifier)
+                .into_iter()
+                .map(|span| &identifier[span])
+                .collect()
+        };
+        assert_eq!(split("parseHttpRequest"), vec!["parse", "Http", "Request"]);
+        assert_eq!(split("HTTPServer"), vec!["HTTP", "Server"]);
+        assert_eq!(split("MAX_RETRY_COUNT"), vec!["MAX", "RETRY", "COUNT"]);
+        assert_eq!(split("snake_case_name"), vec!["snake", "case", "name"]);
+        assert_eq!(split("utf8Decoder"), vec!["utf", "Decoder"]);
+        assert_eq!(split("allURLs"), vec!["all", "URLs"]);
+        assert_eq!(split("don't"), vec!["don't"]);
+        assert_eq!(split("Größe"), vec!["Größe"]);
+    }
+
+    #[test]
+    fn test_check_code() {
+        let speller = speller_from(&[
+            ("parse", 10),
+            ("http", 10),
+            ("request", 10),
+            ("retry", 10),
+            ("count", 10),
+            ("the", 100),
+            ("value", 10),
+            ("is", 50),
+            ("not", 50),
+            ("found", 10),
+            ("in", 50),
+        ]);
+
+        let text = "// parseHttpRequest: the MAX_RETRY_COUNT valeu\nlet xyzzy = \"%s is not\\nfound in parseHtpRequest\";\n";
+        let misspellings = speller.check_code(text, Language::Rust);
+        let words: Vec<&str> = misspellings.iter().map(|m| m.word.as_str()).collect();
+        assert_eq!(words, vec!["MAX", "valeu", "xyzzy", "Htp"]);
+
+        let htp = &misspellings[3];
+        assert_eq!(&text[htp.byte_span.clone()], "Htp");
+        let start = text.find("Htp").unwrap();
+        assert_eq!(htp.char_span, start..start + 3);
+        assert_eq!(htp.suggestions[0], "http");
+
+        // As text, identifiers are unknown words.
+        let words: Vec<String> = speller
+            .check_text("parseHttp")
+            .into_iter()
+            .map(|m| m.word)
+            .collect();
+        assert_eq!(words, vec!["parseHttp"]);
+        assert!(speller
+            .check_code("parse_http_request()", Language::Python)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_check_identifiers() {
+        let speller = speller_from(&[("parse", 10), ("http", 10), ("request", 10)]);
+        let sources = [
+            (Language::Rust, "fn parseHtpRequest() {}"),
+            (Language::Python, "def parseHtpRequest():\n    pass\n"),
+            (Language::JavaScript, "function parseHtpRequest() {}"),
+            (Language::TypeScript, "function parseHtpRequest(): void {}"),
+            (Language::Go, "func parseHtpRequest() {}"),
+        ];
+        for (language, source) in sources {
+            let misspellings = speller.check_code(source, language);
+            let words: Vec<&str> = misspellings.iter().map(|m| m.word.as_str()).collect();
+            assert_eq!(words, vec!["Htp"], "{language:?}");
+            let start = source.find("Htp").unwrap();
+            assert_eq!(misspellings[0].byte_span, start..start + 3);
+            assert_eq!(misspellings[0].suggestions[0], "http");
+        }
+        let source = "fn parseHttpRequest() {}";
+        assert!(speller.check_code(source, Language::Rust).is_empty());
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "cli")]
+mod test_cli {
+    use std::io::Write;
+    use std::process::{Command, Output, Stdio};
+
+    fn speller(args: &[&str], stdin: &str) -> Output {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_speller"))
+            .args(["--dict", "./tests/files/en.txt"])
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(stdin.as_bytes())
+            .unwrap();
+        child.wait_with_output().unwrap()
+    }
+
+    fn stdout(output: &Output) -> String {
+        String::from_utf8(output.stdout.clone()).unwrap()
+    }
+
+    #[test]
+    fn test_human() {
+        let output = speller(&[], "hello world\nthe wrold is big\n");
+        assert_eq!(output.status.code(), Some(1));
+        let stdout = stdout(&output);
+        assert!(stdout.starts_with("<stdin>:2:5: wrold -> world"));
+        assert_eq!(stdout.lines().count(), 1);
+
+        let output = speller(&[], "hello world\n");
+        assert_eq!(output.status.code(), Some(0));
+        assert!(output.stdout.is_empty());
+
+        let output = speller(&["--no-fail"], "the wrold\n");
+        assert_eq!(output.status.code(), Some(0));
+    }
+
+    #[test]
+    fn test_json() {
+        let output = speller(
+            &["--format", "json", "--suggestions", "1"],
+            "hello\n  « wrold\n",
+        );
+        let line: serde_json::Value = serde_json::from_str(stdout(&output).trim()).unwrap();
+        assert_eq!(line["path"], "<stdin>");
+        assert_eq!(line["line"], 2);
+        // Columns are counted in characters.
+        assert_eq!(line["column"], 5);
+        assert_eq!(line["end_column"], 10);
+        assert_eq!(line["word"], "wrold");
+        assert_eq!(line["suggestions"], serde_json::json!(["world"]));
+        // More suggestions than a misspelling carries are looked up.
+        let output = speller(&["--format", "json", "--suggestions", "8"], "wrold\n");
+        let line: serde_json::Value = serde_json::from_str(stdout(&output).trim()).unwrap();
+        assert_eq!(line["suggestions"].as_array().unwrap().len(), 8);
+    }
+
+    #[test]
+    fn test_sarif() {
+        let output = speller(&["--format", "sarif"], "the wrold\n");
+        assert_eq!(output.status.code(), Some(1));
+        let log: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+        assert_eq!(log["version"], "2.1.0");
+        let result = &log["runs"][0]["results"][0];
+        assert_eq!(result["ruleId"], "misspelling");
+        let region = &result["locations"][0]["physicalLocation"]["region"];
+        assert_eq!(region["startLine"], 1);
+        assert_eq!(region["startColumn"], 5);
+        let replacement = &result["fixes"][0]["artifactChanges"][0]["replacements"][0];
+        assert_eq!(replacement["insertedContent"]["text"], "world");
+        let location = &result["locations"][0]["physicalLocation"]["artifactLocation"];
+        assert_eq!(location["uri"], "%3Cstdin%3E");
+
+        let path = scratch_file("sarif", "").with_file_name("my notes.txt");
+        std::fs::write(&path, "the wrold\n").unwrap();
+        let output = speller(&["--format", "sarif", path.to_str().unwrap()], "");
+        let log: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+        let uri = log["runs"][0]["results"][0]["locations"][0]["physicalLocation"]
+            ["artifactLocation"]["uri"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(uri.starts_with("file:///"));
+        assert!(uri.ends_with("/my%20notes.txt"));
+    }
+
+    /// A copy of `text` in a new directory of the test target directory.
+    fn scratch_file(name: &str, text: &str) -> std::path::PathBuf {
+        let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("doc.txt");
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_interactive() {
+        let path = scratch_file("interactive", "Hello wrold.\nThe wrold is teh best.\n");
+        let path = path.to_str().unwrap();
+        // Accept the first candidate, ignore once, then replace by hand.
+        let output = speller(&["--interactive", path], "a\n\nr\nthe\n");
+        assert_eq!(output.status.code(), Some(0));
+        assert!(stdout(&output).contains("0) world"));
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "Hello world.\nThe wrold is the best.\n"
+        );
+        // The file is replaced without leaving the temporary file behind.
+        let dir = std::path::Path::new(path).parent().unwrap();
+        assert_eq!(std::fs::read_dir(dir).unwrap().count(), 1);
+
+        // Quitting leaves the file as it was.
+        std::fs::write(path, "teh wrold\n").unwrap();
+        let output = speller(&["--interactive", path], "0\nq\n");
+        assert_eq!(output.status.code(), Some(0));
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "teh wrold\n");
+    }
+
+    #[test]
+    fn test_personal_dictionary() {
+        let path = scratch_file("personal", "kubectl and kubectl, foobaz and foobaz\n");
+        let personal = path.with_file_name("words.txt");
+        let args = [
+            "--interactive",
+            "--personal",
+            personal.to_str().unwrap(),
+            path.to_str().unwrap(),
+        ];
+        // Add the first word, ignore the second one everywhere.
+        let output = speller(&args, "d\nI\n");
+        assert_eq!(output.status.code(), Some(0));
+        assert_eq!(stdout(&output).matches('?').count(), 2);
+        assert_eq!(std::fs::read_to_string(&personal).unwrap(), "kubectl 1\n");
+
+        // The personal dictionary is a TXT dictionary, and is accepted when reporting.
+        let output = speller(
+            &["--personal", personal.to_str().unwrap()],
+            "kubectl foobaz\n",
+        );
+        assert_eq!(stdout(&output).lines().count(), 1);
+        let output = Command::new(env!("CARGO_BIN_EXE_speller"))
+            .args(["--dict", personal.to_str().unwrap(), "-"])
+            .stdin(Stdio::null())
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(0));
+    }
+
+    #[test]
+    fn test_interactive_case_sensitive() {
+        let path = scratch_file("case_sensitive", "Quxa Foobaz foobaz\n");
+        let personal = path.with_file_name("words.txt");
+        std::fs::write(&personal, "Quxab 1\nQuxac 100\n").unwrap();
+        let args = [
+            "--interactive",
+            "--case-sensitive",
+            "--personal",
+            personal.to_str().unwrap(),
+            path.to_str().unwrap(),
+        ];
+        // The best suggestion comes first, and ignoring "Foobaz" leaves "foobaz" to check.
+        let output = speller(&args, "a\nI\nr\nbar\n");
+        assert_eq!(output.status.code(), Some(0));
+        assert!(stdout(&output).contains("0) Quxac  1) Quxab"));
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "Quxac Foobaz bar\n"
+        );
+    }
+
+    #[test]
+    fn test_pipe() {
+        let input = "hello wrold\n!\n^*teh well-knwon\n@wrold\nthe wrold\n";
+        let output = speller(&["-a", "--suggestions", "2"], input);
+        assert_eq!(output.status.code(), Some(0));
+        let stdout = stdout(&output);
+        let mut lines = stdout.lines();
+        assert!(lines
+            .next()
+            .unwrap()
+            .starts_with("@(#) International Ispell Version 3.1.20"));
+        assert_eq!(lines.next(), Some("*"));
+        assert_eq!(lines.next(), Some("& wrold 2 6: world, wold"));
+        assert_eq!(lines.next(), Some(""));
+        // Terse from here on. Offsets count the leading `^`.
+        assert_eq!(lines.next(), Some("& teh 2 2: the, ten"));
+        assert!(lines.next().unwrap().starts_with("& knwon 2 11: known"));
+        assert_eq!(lines.next(), Some(""));
+        // Accepted for the session.
+        assert_eq!(lines.next(), Some(""));
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn test_pipe_personal_dictionary() {
+        let path = scratch_file("pipe", "");
+        let personal = path.with_file_name("words.txt");
+        let args = ["-a", "--personal", personal.to_str().unwrap()];
+        let output = speller(&args, "*kubectl\n&Foobaz\nkubectl foobaz zzqxv\n#\n");
+        let stdout = stdout(&output);
+        assert_eq!(
+            stdout.lines().skip(1).collect::<Vec<_>>(),
+            ["*", "*", "# zzqxv 15", ""]
+        );
+        assert_eq!(
+            std::fs::read_to_string(&personal).unwrap(),
+            "foobaz 1\nkubectl 1\n"
+        );
+    }
+
+    #[test]
+    fn test_language() {
+        let source = "fn wrold() {}\n// Parse the wrold, see readFileName and LAST_USER_COUNT\n";
+        let output = speller(&["--language", "rust"], source);
+        assert_eq!(output.status.code(), Some(1));
+        let stdout = stdout(&output);
+        assert!(stdout.starts_with("<stdin>:1:4: wrold -> world"));
+        assert!(stdout.contains("\n<stdin>:2:14: wrold -> world"));
+        assert_eq!(stdout.lines().count(), 2);
+
+        // Source files are recognized by their extension.
+        let path = scratch_file("language", "").with_file_name("main.rs");
+        std::fs::write(&path, source).unwrap();
+        let path = path.to_str().unwrap();
+        let output = speller(&[path], "");
+        assert_eq!(self::stdout(&output).lines().count(), 2);
+        let output = speller(&["--language", "text", path], "");
+        assert!(self::stdout(&output).lines().count() > 2);
+    }
+
+    #[test]
+    fn test_errors() {
+        let output = speller(&["./tests/files/missing.txt"], "");
+        assert_eq!(output.status.code(), Some(2));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("missing.txt"));
+
+        let output = Command::new(env!("CARGO_BIN_EXE_speller"))
+            .args(["--dict", "./tests/files/missing.json", "-"])
+            .stdin(Stdio::null())
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(2));
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "lsp")]
+mod test_lsp {
+    use serde_json::{json, Value};
+    use std::collections::VecDeque;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::path::{Path, PathBuf};
+    use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+    /// A language client talking to `speller --lsp` over its standard input and output.
+    struct Client {
+        child: Child,
+        stdin: ChildStdin,
+        stdout: BufReader<ChildStdout>,
+        next_id: i64,
+        /// Notifications received while waiting for a response.
+        notifications: VecDeque<Value>,
+    }
+
+    impl Client {
+        fn start(workspace: &Path) -> Client {
+            Client::start_with(workspace, &[])
+        }
+
+        fn start_with(workspace: &Path, args: &[&str]) -> Client {
+            let mut child = Command::new(env!("CARGO_BIN_EXE_speller"))
+                .args(["--lsp", "--stdio", "--dict", "./tests/files/en.txt"])
+                .args(args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .unwrap();
+            let mut client = Client {
+                stdin: child.stdin.take().unwrap(),
+                stdout: BufReader::new(child.stdout.take().unwrap()),
+                child,
+                next_id: 0,
+                notifications: VecDeque::new(),
+            };
+            let result = client.request(
+                "initialize",
+                json!({
+                    "capabilities": {},
+                    "workspaceFolders": [{ "uri": uri(workspace), "name": "test" }],
+                }),
+            );
+            assert_eq!(result["serverInfo"]["name"], "speller");
+            client.notify("initialized", json!({}));
+            client
+        }
+
+        fn send(&mut self, message: Value) {
+            let body = message.to_string();
+            write!(self.stdin, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+            self.stdin.flush().unwrap();
+        }
+
+        fn receive(&mut self) -> Value {
+            let mut length = 0;
+            loop {
+                let mut header = String::new();
+                self.stdout.read_line(&mut header).unwrap();
+                let header = header.trim();
+                if header.is_empty() {
+                    break;
+                }
+                if let Some(value) = header.strip_prefix("Content-Length: ") {
+                    length = value.parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            self.stdout.read_exact(&mut body).unwrap();
+            serde_json::from_slice(&body).unwrap()
+        }
+
+        fn notify(&mut self, method: &str, params: Value) {
+            self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+        }
+
+        fn request(&mut self, method: &str, params: Value) -> Value {
+            self.next_id += 1;
+            let id = self.next_id;
+            self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+            loop {
+                let message = self.receive();
+                if message["id"] == id {
+                    return message["result"].clone();
+                }
+                self.notifications.push_back(message);
+            }
+        }
+
+        /// The diagnostics of the next `publishDiagnostics` notification.
+        fn diagnostics(&mut self) -> Vec<Value> {
+            loop {
+                let message = match self.notifications.pop_front() {
+                    Some(message) => message,
+                    None => self.receive(),
+                };
+                if message["method"] == "textDocument/publishDiagnostics" {
+                    return message["params"]["diagnostics"].as_array().unwrap().clone();
+                }
+            }
+        }
+
+        fn open(&mut self, uri: &str, text: &str) {
+            self.notify(
+                "textDocument/didOpen",
+                json!({
+                    "textDocument": { "uri": uri, "languageId": "markdown", "version": 1, "text": text },
+                }),
+            );
+        }
+
+        fn stop(mut self) {
+            self.request("shutdown", Value::Null);
+            self.notify("exit", Value::Null);
+            drop(self.stdin);
+            assert!(self.child.wait().unwrap().success());
+        }
+    }
+
+    fn uri(path: &Path) -> String {
+        format!("file://{}", path.display())
+    }
+
+    fn workspace(name: &str) -> PathBuf {
+        let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let root = workspace("lsp_diagnostics");
+        let mut client = Client::start(&root);
+        let doc = uri(&root.join("doc.md"));
+        client.open(&doc, "Hello world.\n😀 The wrold\n");
+        let diagnostics = client.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["source"], "speller");
+        // Columns are counted in UTF-16 code units.
+        assert_eq!(
+            diagnostics[0]["range"],
+            json!({ "start": { "line": 1, "character": 7 }, "end": { "line": 1, "character": 12 } })
+        );
+
+        client.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": doc, "version": 2 },
+                "contentChanges": [{ "text": "Hello world.\n" }],
+            }),
+        );
+        assert!(client.diagnostics().is_empty());
+
+        // Source files are checked as code: keywords are skipped.
+        let source = uri(&root.join("main.rs"));
+        client.open(&source, "fn wrold() {}\n// the wrold\n");
+        let diagnostics = client.diagnostics();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0]["range"],
+            json!({ "start": { "line": 0, "character": 3 }, "end": { "line": 0, "character": 8 } })
+        );
+        assert_eq!(diagnostics[1]["range"]["start"]["line"], 1);
+        client.stop();
+    }
+
+    #[test]
+    fn test_code_actions() {
+        let root = workspace("lsp_code_actions");
+        let mut client = Client::start(&root);
+        let doc = uri(&root.join("doc.md"));
+        client.open(&doc, "The wrold is big.\n");
+        let diagnostics = client.diagnostics();
+
+        let actions = client.request(
+            "textDocument/codeAction",
+            json!({
+                "textDocument": { "uri": doc },
+                "range": diagnostics[0]["range"],
+                "context": { "diagnostics": diagnostics },
+            }),
+        );
+        let actions = actions.as_array().unwrap();
+        assert_eq!(actions[0]["title"], "Change to \"world\"");
+        assert_eq!(actions[0]["isPreferred"], true);
+        let edit = &actions[0]["edit"]["changes"][&doc][0];
+        assert_eq!(edit["newText"], "world");
+        assert_eq!(edit["range"], diagnostics[0]["range"]);
+
+        let add = actions.last().unwrap();
+        assert_eq!(add["title"], "Add \"wrold\" to the workspace dictionary");
+        let command = &add["command"];
+        client.request(
+            "workspace/executeCommand",
+            json!({ "command": command["command"], "arguments": command["arguments"] }),
+        );
+        assert!(client.diagnostics().is_empty());
+        assert_eq!(
+            std::fs::read_to_string(root.join(".speller-words")).unwrap(),
+            "wrold 1\n"
+        );
+        client.stop();
+
+        // The workspace dictionary is loaded by the next server.
+        let mut client = Client::start(&root);
+        client.open(&doc, "The wrold is big.\n");
+        assert!(client.diagnostics().is_empty());
+        client.stop();
+    }
+
+    #[test]
+    fn test_personal_dictionary() {
+        let dir = workspace("lsp_personal");
+        let root = dir.join("project");
+        std::fs::create_dir(&root).unwrap();
+        let personal = dir.join("words.txt");
+        let mut client = Client::start_with(&root, &["--personal", personal.to_str().unwrap()]);
+        let inside = uri(&root.join("doc.md"));
+        let outside = uri(&dir.join("notes.md"));
+        client.open(&inside, "foobaz\n");
+        assert_eq!(client.diagnostics().len(), 1);
+        client.open(&outside, "foobaz\n");
+        assert_eq!(client.diagnostics().len(), 1);
+
+        // A word added to the personal dictionary is known in the workspace too.
+        client.request(
+            "workspace/executeCommand",
+            json!({ "command": "speller.addToDictionary", "arguments": [outside, "foobaz"] }),
+        );
+        assert!(client.diagnostics().is_empty());
+        assert!(client.diagnostics().is_empty());
+        assert_eq!(std::fs::read_to_string(&personal).unwrap(), "foobaz 1\n");
+        client.stop();
+    }
+}